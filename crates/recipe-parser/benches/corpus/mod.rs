@@ -0,0 +1,45 @@
+//! Recipes the benchmarks parse
+
+// Each benchmark uses only part of the corpus
+#![allow(dead_code)]
+
+/// The recipes of the examples folder, by file name
+pub const EXAMPLES: &[(&str, &str)] = &[
+    (
+        "buddha-bowl",
+        include_str!("../../../../examples/buddha-bowl.recp"),
+    ),
+    ("hummus", include_str!("../../../../examples/hummus.recp")),
+    (
+        "potatoes-jean-claud",
+        include_str!("../../../../examples/potatoes-jean-claud.recp"),
+    ),
+    (
+        "spaghetti-a-la-veganesa",
+        include_str!("../../../../examples/spaghetti-a-la-veganesa.recp"),
+    ),
+];
+
+/// A recipe book of at least `size` bytes, made of the examples without their backstory
+pub fn book(size: usize) -> String {
+    let recipes: Vec<&str> = EXAMPLES
+        .iter()
+        .map(|(_, recipe)| recipe.split("\n---\n").next().unwrap())
+        .collect();
+    let mut book = String::new();
+    while book.len() < size {
+        for recipe in &recipes {
+            book.push_str(recipe);
+            book.push_str("\n\n");
+        }
+    }
+    book
+}
+
+/// Steps of at least `size` bytes with words only, like long instructions or notes
+pub fn prose(size: usize) -> String {
+    let paragraph = "Stir the sauce gently with a wooden spoon until it thickens, \
+        then season to taste and let it rest for a couple of minutes.\n\
+        Meanwhile, warm the plates: it's the detail that makes the difference.\n\n";
+    paragraph.repeat(size.div_ceil(paragraph.len()))
+}
@@ -0,0 +1,22 @@
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use recipe_parser::parse;
+
+mod corpus;
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    let book = corpus::book(1024 * 1024);
+    let prose = corpus::prose(1024 * 1024);
+    let inputs = corpus::EXAMPLES
+        .iter()
+        .copied()
+        .chain([("book", book.as_str()), ("prose", prose.as_str())]);
+    for (name, input) in inputs {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(name, |b| b.iter(|| parse(black_box(input)).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
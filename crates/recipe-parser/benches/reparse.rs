@@ -0,0 +1,28 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use recipe_parser::{Edit, parse_with_spans, reparse};
+
+mod corpus;
+
+fn bench_reparse(c: &mut Criterion) {
+    let source = corpus::book(4 * 1024 * 1024);
+    let tokens = parse_with_spans(&source).unwrap();
+    let middle = source[..source.len() / 2].rfind('\n').unwrap() + 1;
+    let edit = Edit {
+        range: middle..middle,
+        replacement: "Season with {salt}(1 pinch).\n",
+    };
+    let input = edit.apply(&source);
+
+    let mut group = c.benchmark_group("edit in a 4 MB recipe book");
+    group.sample_size(20);
+    group.bench_function("full parse", |b| {
+        b.iter(|| parse_with_spans(black_box(&input)).unwrap())
+    });
+    group.bench_function("reparse", |b| {
+        b.iter(|| reparse(black_box(&source), &tokens, &edit, black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_reparse);
+criterion_main!(benches);
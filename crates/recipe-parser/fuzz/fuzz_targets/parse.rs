@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use recipe_parser::{parse, parse_with_spans, serialize};
+
+fuzz_target!(|input: &str| {
+    let Ok(tokens) = parse_with_spans(input) else {
+        return;
+    };
+
+    // The spans follow each other and cover the input
+    let mut end = 0;
+    for (_, span) in &tokens {
+        assert_eq!(span.start, end);
+        assert!(span.start < span.end && span.end <= input.len());
+        assert!(input.is_char_boundary(span.start) && input.is_char_boundary(span.end));
+        end = span.end;
+    }
+    assert_eq!(end, input.len());
+
+    // Printing the tokens gives a recipe with the same tokens
+    let tokens: Vec<_> = tokens.into_iter().map(|(token, _)| token).collect();
+    let serialized = serialize(&tokens);
+    let reparsed = parse(&serialized).expect("the serialized recipe could not be parsed");
+    assert_eq!(tokens, reparsed);
+});
@@ -0,0 +1,71 @@
+//! Runs the conformance corpus, in the `conformance` folder of the repository.
+//!
+//! Each `<case>.recp` has a `<case>.json` with the expected tokens and their spans,
+//! or the offset of the error. Run with `UPDATE_CONFORMANCE=1` to write the
+//! expected files after a change in the grammar.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+
+use crate::parse_with_spans;
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../conformance");
+    let mut cases: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("could not read {}: {err}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "recp"))
+        .collect();
+    cases.sort();
+    cases
+}
+
+/// The tokens in the JSON of the `serde` feature, with a `span` of byte offsets
+fn output(input: &str) -> Value {
+    match parse_with_spans(input) {
+        Ok(tokens) => tokens
+            .iter()
+            .map(|(token, span)| {
+                let mut value = serde_json::to_value(token).unwrap();
+                value["span"] = json!([span.start, span.end]);
+                value
+            })
+            .collect(),
+        Err(error) => json!({ "error": { "offset": error.offset() } }),
+    }
+}
+
+#[test]
+fn test_conformance_corpus() {
+    let update = std::env::var_os("UPDATE_CONFORMANCE").is_some();
+    let cases = corpus();
+    assert!(!cases.is_empty(), "the conformance corpus is empty");
+    let mut failures = vec![];
+    for case in cases {
+        let input = fs::read_to_string(&case).unwrap();
+        let output = output(&input);
+        let expected_path = case.with_extension("json");
+        if update {
+            let json = serde_json::to_string_pretty(&output).unwrap();
+            fs::write(&expected_path, json + "\n").unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|err| panic!("could not read {}: {err}", expected_path.display()));
+        let expected: Value = serde_json::from_str(&expected).unwrap();
+        if output != expected {
+            failures.push(format!(
+                "{}:\n{}",
+                case.display(),
+                serde_json::to_string_pretty(&output).unwrap()
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "the tokens don't match the expected ones in\n{}",
+        failures.join("\n")
+    );
+}
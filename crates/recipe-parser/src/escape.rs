@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+
+/// Characters that can be escaped with a backslash.
+///
+/// Any ASCII punctuation can be escaped, e.g: `\{`, `\}`, `\(`, `\\`.
+/// A backslash followed by anything else is just a backslash.
+pub(crate) fn is_escapable(c: char) -> bool {
+    c.is_ascii_punctuation()
+}
+
+/// Characters that `escape` will always prefix with a backslash.
+const ALWAYS_ESCAPED: &str = "\\{}()";
+
+/// Remove the escaping backslashes from a raw token value.
+///
+/// Tokens borrow the raw text from the recipe, so an ingredient written as
+/// `{a \} b}` has the name `a \} b`. Use this function to get the literal `a } b`.
+///
+/// ```
+/// use recipe_parser::unescape;
+///
+/// assert_eq!(unescape(r"t\{ is not a timer"), "t{ is not a timer");
+/// assert_eq!(unescape(r"C:\Users"), r"C:\Users");
+/// ```
+pub fn unescape(input: &str) -> Cow<'_, str> {
+    if !input.contains('\\') {
+        return Cow::Borrowed(input);
+    }
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if is_escapable(next) => {
+                output.push(next);
+                chars.next();
+            }
+            _ => output.push(c),
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Escape a literal text so it can be written in a recipe as prose or
+/// inside the curly braces of a tag.
+///
+/// ```
+/// use recipe_parser::escape;
+///
+/// assert_eq!(escape("crème fraîche {full fat}"), r"crème fraîche \{full fat\}");
+/// assert_eq!(escape("a >> b"), r"a \>> b");
+/// ```
+pub fn escape(input: &str) -> Cow<'_, str> {
+    let needs_escape = |c: char, next: Option<char>| {
+        ALWAYS_ESCAPED.contains(c)
+            // start of metadata
+            || (c == '>' && next == Some('>'))
+            // start of a section
+            || (c == '=' && next == Some('='))
+            // start of a comment
+            || (c == '/' && next == Some('*'))
+    };
+    let mut chars = input.chars().peekable();
+    let mut output: Option<String> = None;
+    let mut idx = 0;
+    while let Some(c) = chars.next() {
+        if needs_escape(c, chars.peek().copied()) {
+            let out = output.get_or_insert_with(|| {
+                let mut s = String::with_capacity(input.len() + 1);
+                s.push_str(&input[..idx]);
+                s
+            });
+            out.push('\\');
+            out.push(c);
+        } else if let Some(out) = output.as_mut() {
+            out.push(c);
+        }
+        idx += c.len_utf8();
+    }
+    match output {
+        Some(out) => Cow::Owned(out),
+        None => Cow::Borrowed(input),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("salt", "salt")]
+    #[case(r"\{salt\}", "{salt}")]
+    #[case(r"t\{", "t{")]
+    #[case(r"a \\ b", r"a \ b")]
+    #[case(r"C:\Users", r"C:\Users")]
+    #[case(r"ends with \", r"ends with \")]
+    #[case(r"crème \(fraîche\)", "crème (fraîche)")]
+    fn test_unescape(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(unescape(input), expected)
+    }
+
+    #[rstest]
+    #[case("salt", "salt")]
+    #[case("{salt}", r"\{salt\}")]
+    #[case(r"a \ b", r"a \\ b")]
+    #[case("a /* b", r"a \/* b")]
+    #[case("1 > 2", "1 > 2")]
+    #[case("== a ==", r"\== a \==")]
+    #[case("crème fraîche (full fat)", r"crème fraîche \(full fat\)")]
+    fn test_escape(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(escape(input), expected)
+    }
+
+    #[rstest]
+    #[case("crème fraîche (full fat)")]
+    #[case(r"weird \ {value} >> /* here */")]
+    fn test_escape_roundtrip(#[case] input: &str) {
+        assert_eq!(unescape(&escape(input)), input)
+    }
+}
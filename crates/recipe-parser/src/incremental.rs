@@ -0,0 +1,497 @@
+use std::ops::Range;
+
+use winnow::combinator::opt;
+use winnow::error::{ContextError, ParseError};
+use winnow::stream::Stream;
+use winnow::{LocatingSlice, Parser};
+
+use crate::options::{Error, ParseOptions, tidy};
+use crate::parser::{
+    Amount, SpannedToken, Syntax, Token, front_matter, recipe_value, recipe_with_spans,
+};
+
+/// A change to the text of a recipe, the byte `range` is replaced by `replacement`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit<'e> {
+    pub range: Range<usize>,
+    pub replacement: &'e str,
+}
+
+impl Edit<'_> {
+    /// The text after the edit
+    ///
+    /// # Panics
+    ///
+    /// When the range is out of the source or not on char boundaries.
+    ///
+    /// ```
+    /// use recipe_parser::Edit;
+    ///
+    /// let edit = Edit { range: 5..9, replacement: "{potatoes}" };
+    /// assert_eq!(edit.apply("Boil eggs"), "Boil {potatoes}");
+    /// ```
+    pub fn apply(&self, source: &str) -> String {
+        let mut text =
+            String::with_capacity(source.len() - self.range.len() + self.replacement.len());
+        text.push_str(&source[..self.range.start]);
+        text.push_str(self.replacement);
+        text.push_str(&source[self.range.end..]);
+        text
+    }
+}
+
+/// Move a token parsed from `source` to the same text in `input`, `shift` bytes away.
+///
+/// `None` when the token doesn't borrow from `source`, like a token parsed from a copy.
+fn rebase<'a>(token: &Token<'_>, source: &str, input: &'a str, shift: isize) -> Option<Token<'a>> {
+    let slice = |value: &str| -> Option<&'a str> {
+        if value.is_empty() {
+            return Some("");
+        }
+        let offset = (value.as_ptr() as usize).checked_sub(source.as_ptr() as usize)?;
+        if offset + value.len() > source.len() {
+            return None;
+        }
+        let start = offset.wrapping_add_signed(shift);
+        input.get(start..start + value.len())
+    };
+    let optional = |value: Option<&str>| match value {
+        Some(value) => slice(value).map(Some),
+        None => Some(None),
+    };
+    let moved_amount = |amount: &Amount<'_>| {
+        Some(Amount {
+            quantity: optional(amount.quantity)?,
+            unit: optional(amount.unit)?,
+        })
+    };
+    let token = match token {
+        Token::Metadata { key, value } => Token::Metadata {
+            key: slice(key)?,
+            value: slice(value)?,
+        },
+        Token::Ingredient {
+            name,
+            quantity,
+            unit,
+            alternatives,
+            note,
+            optional: is_optional,
+        } => Token::Ingredient {
+            name: slice(name)?,
+            quantity: optional(*quantity)?,
+            unit: optional(*unit)?,
+            alternatives: alternatives
+                .iter()
+                .map(moved_amount)
+                .collect::<Option<_>>()?,
+            note: optional(*note)?,
+            optional: *is_optional,
+        },
+        Token::RecipeRef {
+            name,
+            quantity,
+            unit,
+        } => Token::RecipeRef {
+            name: slice(name)?,
+            quantity: optional(*quantity)?,
+            unit: optional(*unit)?,
+        },
+        Token::Timer(value) => Token::Timer(slice(value)?),
+        Token::Material(value) => Token::Material(slice(value)?),
+        Token::Temperature { value, scale } => Token::Temperature {
+            value: slice(value)?,
+            scale: *scale,
+        },
+        Token::Tag {
+            prefix,
+            body,
+            amount,
+        } => Token::Tag {
+            prefix: slice(prefix)?,
+            body: slice(body)?,
+            amount: match amount {
+                Some(amount) => Some(moved_amount(amount)?),
+                None => None,
+            },
+        },
+        Token::Word(value) => Token::Word(slice(value)?),
+        Token::Space(value) => Token::Space(slice(value)?),
+        Token::Comment(value) => Token::Comment(slice(value)?),
+        Token::Backstory(value) => Token::Backstory(slice(value)?),
+        Token::Section(value) => Token::Section(slice(value)?),
+        Token::FrontMatter { format, content } => Token::FrontMatter {
+            format: *format,
+            content: slice(content)?,
+        },
+    };
+    Some(token)
+}
+
+/// Update the tokens of `source` after an edit, `input` is the source with the
+/// edit applied.
+///
+/// Only the region around the edit is parsed again: parsing restarts at the line
+/// before the edit, because tokens like metadata continue on the next line, and
+/// stops as soon as a token ends where a token of the previous parse, past the
+/// edit, starts. The tokens before and after that region are moved to `input`.
+///
+/// The result is the same as [`parse_with_spans`](crate::parse_with_spans) on `input`.
+/// The `tokens` must be parsed from `source` itself: tokens of a copy of the text
+/// can't be moved, and `input` is parsed again in full.
+///
+/// ```
+/// use recipe_parser::{Edit, Token, parse_with_spans, reparse};
+///
+/// let source = "Boil {potatoes}\nAdd salt";
+/// let tokens = parse_with_spans(source).unwrap();
+/// let edit = Edit { range: 20..24, replacement: "{salt}(1 pinch)" };
+/// let input = edit.apply(source);
+/// let tokens = reparse(source, &tokens, &edit, &input).unwrap();
+/// assert_eq!(tokens, parse_with_spans(&input).unwrap());
+/// ```
+///
+/// # Panics
+///
+/// When the range of the edit is out of the source or not on char boundaries, or
+/// `input` is not the same length as the source with the edit applied.
+pub fn reparse<'s, 'a>(
+    source: &'s str,
+    tokens: &[SpannedToken<'s>],
+    edit: &Edit,
+    input: &'a str,
+) -> Result<Vec<SpannedToken<'a>>, ParseError<LocatingSlice<&'a str>, ContextError>> {
+    reparse_syntax(source, tokens, edit, input, Syntax::default())
+}
+
+/// Like [`reparse`], for the tokens of [`parse_with`](crate::parse_with).
+///
+/// The result is the same as the tokens of `parse_with` on `input` with the same
+/// options. The limits and the spec version are only checked by `parse_with`.
+///
+/// ```
+/// use recipe_parser::{Edit, ParseOptions, TagRegistry, parse_with, reparse_with};
+///
+/// let options = ParseOptions {
+///     tags: TagRegistry::from_iter(['%']),
+///     ..Default::default()
+/// };
+/// let source = "Bake in %{oven}";
+/// let tokens = parse_with(source, &options).unwrap().tokens;
+/// let edit = Edit { range: 0..4, replacement: "Roast" };
+/// let input = edit.apply(source);
+/// let tokens = reparse_with(source, &tokens, &edit, &input, &options).unwrap();
+/// assert_eq!(tokens, parse_with(&input, &options).unwrap().tokens);
+/// ```
+///
+/// # Panics
+///
+/// Like [`reparse`].
+pub fn reparse_with<'s, 'a>(
+    source: &'s str,
+    tokens: &[SpannedToken<'s>],
+    edit: &Edit,
+    input: &'a str,
+    options: &ParseOptions,
+) -> Result<Vec<SpannedToken<'a>>, Error<'a>> {
+    let tokens = reparse_syntax(source, tokens, edit, input, options.syntax()?)?;
+    Ok(tidy(input, tokens, options))
+}
+
+fn reparse_syntax<'s, 'a>(
+    source: &'s str,
+    tokens: &[SpannedToken<'s>],
+    edit: &Edit,
+    input: &'a str,
+    syntax: Syntax<'_>,
+) -> Result<Vec<SpannedToken<'a>>, ParseError<LocatingSlice<&'a str>, ContextError>> {
+    let full_parse = || recipe_with_spans(syntax).parse(LocatingSlice::new(input));
+    let Edit { range, replacement } = edit;
+    assert!(
+        range.start <= range.end && range.end <= source.len(),
+        "edit range {range:?} is out of the source"
+    );
+    assert!(
+        source.is_char_boundary(range.start) && source.is_char_boundary(range.end),
+        "edit range {range:?} is not on char boundaries"
+    );
+    assert_eq!(
+        input.len(),
+        source.len() - range.len() + replacement.len(),
+        "the input is not the source with the edit applied"
+    );
+    let shift = replacement.len() as isize - range.len() as isize;
+
+    let line_start = |end: usize| source[..end].rfind('\n').map_or(0, |i| i + 1);
+    let previous_line = match line_start(range.start) {
+        0 => 0,
+        start => line_start(start - 1),
+    };
+    let has_front_matter = matches!(tokens.first(), Some((Token::FrontMatter { .. }, _)));
+    // A fence far from the edit can become a front matter once it's closed
+    let first = if !has_front_matter
+        && syntax.front_matter
+        && (input.starts_with("---") || input.starts_with("+++"))
+    {
+        0
+    } else {
+        tokens.partition_point(|(_, span)| span.end < previous_line)
+    };
+    let restart = tokens.get(first).map_or(0, |(_, span)| span.start);
+
+    let mut stream = LocatingSlice::new(input);
+    stream.next_slice(restart);
+    let mut reparsed = Vec::new();
+    if restart == 0 && syntax.front_matter {
+        match opt(front_matter.with_span()).parse_next(&mut stream) {
+            Ok(token) => reparsed.extend(token),
+            Err(_) => return full_parse(),
+        }
+    }
+    // Tokens of the previous parse after the edit, they are kept from the first
+    // one that starts where a reparsed token ends. A front matter is only valid at
+    // the beginning, so it's never kept after an edit in front of it.
+    let mut next = tokens
+        .partition_point(|(_, span)| span.start < range.end)
+        .max(usize::from(has_front_matter));
+    let mut value = recipe_value(syntax).with_span();
+    loop {
+        let position = input.len() - stream.eof_offset();
+        while next < tokens.len() && tokens[next].1.start.wrapping_add_signed(shift) < position {
+            next += 1;
+        }
+        // A section is only a section at the start of a line
+        let in_sync = next < tokens.len()
+            && tokens[next].1.start.wrapping_add_signed(shift) == position
+            && source[..tokens[next].1.start].ends_with('\n') == input[..position].ends_with('\n');
+        if in_sync || stream.eof_offset() == 0 {
+            break;
+        }
+        match value.parse_next(&mut stream) {
+            Ok(token) => reparsed.push(token),
+            // The full parse reports the error
+            Err(_) => return full_parse(),
+        }
+    }
+
+    let moved = |(token, span): &SpannedToken<'_>, shift: isize| {
+        Some((
+            rebase(token, source, input, shift)?,
+            span.start.wrapping_add_signed(shift)..span.end.wrapping_add_signed(shift),
+        ))
+    };
+    let before: Option<Vec<_>> = tokens[..first]
+        .iter()
+        .map(|token| moved(token, 0))
+        .collect();
+    let after: Option<Vec<_>> = tokens[next..]
+        .iter()
+        .map(|token| moved(token, shift))
+        .collect();
+    let (Some(before), Some(after)) = (before, after) else {
+        return full_parse();
+    };
+    let mut result = Vec::with_capacity(before.len() + reparsed.len() + after.len());
+    result.extend(before);
+    result.extend(reparsed);
+    result.extend(after);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Mode, TagRegistry, parse_with, parse_with_spans};
+
+    const RECIPE: &str = "---
+name: lentil soup
+---
+>> tags: vegan, soup
+>> description: a warm soup
+   for cold days
+== Base ==
+Chop the {onion}(1, finely chopped) and the {carrots}?(2 | 200 g).
+Fry in &{pot} at °{180 C} with {olive oil}(2 tbsp) for t{5 minutes}. /* not too long */
+
+== Soup ==
+Add @{vegetable stock}(1 l) and {lentils}(250 g), cook t{30 minutes} in %{oven}(1).
+Rest ~{5 minutes}, season with {salt} and l'{pepper}.
+---
+My grandma made it every winter.
+";
+
+    const REPLACEMENTS: &[&str] = &[
+        "",
+        "x",
+        "\n",
+        "   ",
+        "{",
+        "}",
+        "(1 kg)",
+        "t{",
+        "\n---\n",
+        ">> a: b\n",
+        "not a key\n",
+        "*/",
+        "==",
+        "%{",
+        "~{",
+    ];
+
+    fn assert_reparse(source: &str, tokens: &[SpannedToken<'_>], edit: &Edit) {
+        let input = edit.apply(source);
+        let expected = parse_with_spans(&input);
+        let reparsed = reparse(source, tokens, edit, &input);
+        match expected {
+            Ok(expected) => assert_eq!(reparsed.unwrap(), expected, "{edit:?}"),
+            Err(_) => assert!(reparsed.is_err(), "{edit:?}"),
+        }
+    }
+
+    fn assert_reparse_with(
+        source: &str,
+        tokens: &[SpannedToken<'_>],
+        edit: &Edit,
+        options: &ParseOptions,
+    ) {
+        let input = edit.apply(source);
+        let expected = parse_with(&input, options);
+        let reparsed = reparse_with(source, tokens, edit, &input, options);
+        match expected {
+            Ok(expected) => assert_eq!(reparsed.unwrap(), expected.tokens, "{edit:?}"),
+            Err(_) => assert!(reparsed.is_err(), "{edit:?}"),
+        }
+    }
+
+    #[test]
+    fn test_reparse_matches_full_parse() {
+        let tokens = parse_with_spans(RECIPE).unwrap();
+        let options = ParseOptions {
+            mode: Mode::Lenient,
+            keep_comments: false,
+            merge_whitespace: true,
+            timer_sigil: '~',
+            tags: TagRegistry::from_iter(['%']),
+            front_matter: false,
+            ..Default::default()
+        };
+        let tokens_with = parse_with(RECIPE, &options).unwrap().tokens;
+        let boundaries: Vec<usize> = (0..=RECIPE.len())
+            .filter(|i| RECIPE.is_char_boundary(*i))
+            .collect();
+        for (index, start) in boundaries.iter().enumerate() {
+            for end in boundaries[index..].iter().take(2) {
+                for replacement in REPLACEMENTS {
+                    let edit = Edit {
+                        range: *start..*end,
+                        replacement,
+                    };
+                    assert_reparse(RECIPE, &tokens, &edit);
+                    assert_reparse_with(RECIPE, &tokens_with, &edit, &options);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reparse_continues_metadata() {
+        let source = ">> description: a soup\nfor cold days";
+        let edit = Edit {
+            range: 23..23,
+            replacement: "  ",
+        };
+        let input = edit.apply(source);
+        let tokens = reparse(source, &parse_with_spans(source).unwrap(), &edit, &input).unwrap();
+        assert_eq!(
+            tokens,
+            vec![(
+                Token::Metadata {
+                    key: "description",
+                    value: "a soup\n  for cold days"
+                },
+                0..input.len()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reparse_closes_front_matter() {
+        let source = "---\nname: soup\n\nBoil {water}\n";
+        assert_reparse(
+            source,
+            &parse_with_spans(source).unwrap(),
+            &Edit {
+                range: 15..15,
+                replacement: "---\n",
+            },
+        );
+    }
+
+    #[test]
+    fn test_reparse_keeps_tokens_away_from_the_edit() {
+        let source = "Boil {water}\nAdd {salt}\nServe {soup}";
+        let tokens = parse_with_spans(source).unwrap();
+        let edit = Edit {
+            range: 5..12,
+            replacement: "{milk}(1 l)",
+        };
+        let input = edit.apply(source);
+        let reparsed = reparse(source, &tokens, &edit, &input).unwrap();
+        let (last, span) = reparsed.last().unwrap();
+        assert_eq!(
+            *last,
+            Token::Ingredient {
+                name: "soup",
+                quantity: None,
+                unit: None,
+                alternatives: vec![],
+                note: None,
+                optional: false,
+            }
+        );
+        assert_eq!(&input[span.clone()], "{soup}");
+    }
+
+    #[test]
+    fn test_reparse_reports_errors() {
+        let source = "Boil {water}";
+        let edit = Edit {
+            range: 11..12,
+            replacement: "",
+        };
+        let input = edit.apply(source);
+        assert!(reparse(source, &parse_with_spans(source).unwrap(), &edit, &input).is_err());
+    }
+
+    #[test]
+    fn test_reparse_tokens_of_a_copy() {
+        let source = "Boil {water}\nAdd {salt}\nServe {soup}";
+        let copy = source.to_string();
+        let tokens = parse_with_spans(&copy).unwrap();
+        let edit = Edit {
+            range: 5..12,
+            replacement: "{milk}(1 l)",
+        };
+        let input = edit.apply(source);
+        assert_eq!(
+            reparse(source, &tokens, &edit, &input).unwrap(),
+            parse_with_spans(&input).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not on char boundaries")]
+    fn test_reparse_edit_inside_a_char() {
+        let source = "Add {jalapeño}";
+        let edit = Edit {
+            range: 12..13,
+            replacement: "",
+        };
+        let _ = reparse(
+            source,
+            &parse_with_spans(source).unwrap(),
+            &edit,
+            "Add {jalape}",
+        );
+    }
+}
@@ -1,4 +1,18 @@
+#[cfg(all(test, feature = "serde"))]
+mod conformance;
+mod escape;
+mod incremental;
+mod options;
 mod parser;
+#[cfg(test)]
+mod properties;
+mod serializer;
+mod version;
 
-pub use parser::Token;
-pub use parser::parse;
+pub use escape::{escape, unescape};
+pub use incremental::{Edit, reparse, reparse_with};
+pub use options::{Error, Mode, ParseOptions, Parsed, TagRegistry, parse_with};
+pub use parser::{Amount, FrontMatterFormat, SpannedToken, TemperatureScale, Token};
+pub use parser::{parse, parse_with_spans};
+pub use serializer::serialize;
+pub use version::{SPEC_VERSION, SpecVersion, Unsupported, VERSION_KEY};
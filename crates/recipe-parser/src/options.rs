@@ -0,0 +1,761 @@
+use std::fmt::Display;
+
+use winnow::LocatingSlice;
+use winnow::error::{ContextError, ParseError};
+
+use winnow::Parser;
+
+use crate::parser::{
+    FrontMatterFormat, SpannedToken, Syntax, Token, front_matter, recipe_with_spans,
+};
+use crate::version::{SPEC_VERSION, SpecVersion, Unsupported, declared_version, unsupported};
+
+/// What to do with text that is not valid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// A tag that can't be parsed, like `{salt` without the closing brace, is an error
+    #[default]
+    Strict,
+    /// A tag that can't be parsed is a word
+    Lenient,
+}
+
+/// Prefixes of `{}` tags declared by an application, besides the ones of timers,
+/// materials and recipe references. Their tags are parsed as [`Token::Tag`], and
+/// the prefixes that are not registered are words.
+///
+/// ```
+/// use recipe_parser::{ParseOptions, TagRegistry, Token, parse_with};
+///
+/// let mut tags = TagRegistry::default();
+/// tags.register('%');
+/// let options = ParseOptions { tags, ..Default::default() };
+/// let parsed = parse_with("%{oven 180C}", &options).unwrap();
+/// assert!(matches!(parsed.tokens[0].0, Token::Tag { prefix: "%", body: "oven 180C", .. }));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagRegistry {
+    prefixes: Vec<char>,
+}
+
+impl TagRegistry {
+    pub fn register(&mut self, prefix: char) -> &mut Self {
+        if !self.prefixes.contains(&prefix) {
+            self.prefixes.push(prefix);
+        }
+        self
+    }
+
+    pub fn prefixes(&self) -> &[char] {
+        &self.prefixes
+    }
+}
+
+impl FromIterator<char> for TagRegistry {
+    fn from_iter<T: IntoIterator<Item = char>>(prefixes: T) -> Self {
+        let mut registry = TagRegistry::default();
+        for prefix in prefixes {
+            registry.register(prefix);
+        }
+        registry
+    }
+}
+
+/// How [`parse_with`] parses a recipe
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Version of the spec to check the recipe against. When it's not set, the
+    /// version declared with `>> recp-version: 0.2` is used, or else
+    /// [`SPEC_VERSION`].
+    pub version: Option<SpecVersion>,
+    /// Fail on constructs the version doesn't support, instead of warning
+    pub deny_unsupported: bool,
+    pub mode: Mode,
+    /// Keep the comments as tokens, their spans are left out otherwise
+    pub keep_comments: bool,
+    /// Join spaces that follow each other, like the spaces at the end of a line
+    /// and the line break, in one token
+    pub merge_whitespace: bool,
+    /// Character before the curly braces of a timer, `t` by default
+    pub timer_sigil: char,
+    /// Character before the curly braces of a material, `&` by default
+    pub material_sigil: char,
+    /// Prefixes of the custom tags
+    pub tags: TagRegistry,
+    /// Characters allowed in the names of ingredients, materials, timers and
+    /// recipe references, after unescaping them. Any character when not set.
+    pub name_chars: Option<fn(char) -> bool>,
+    /// Read a `---` or `+++` block at the beginning as a front matter, when `false`
+    /// it's parsed like the rest of the recipe
+    pub front_matter: bool,
+    /// Deepest nesting of lists and tables in the front matter, the only part of a
+    /// recipe that nests. It's checked before the rest of the recipe is parsed, and
+    /// approximated from the brackets and the indentation, without parsing the YAML
+    /// or TOML, so it can be more than the real nesting.
+    pub max_front_matter_nesting: Option<usize>,
+    /// Size of the recipe in bytes
+    pub max_input_size: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            version: None,
+            deny_unsupported: false,
+            mode: Mode::Strict,
+            keep_comments: true,
+            merge_whitespace: false,
+            timer_sigil: 't',
+            material_sigil: '&',
+            tags: TagRegistry::default(),
+            name_chars: None,
+            front_matter: true,
+            max_front_matter_nesting: None,
+            max_input_size: None,
+        }
+    }
+}
+
+/// Symbols that start other tokens, they can't be sigils
+const RESERVED: &str = "{}()\\'`>=@/°?";
+
+impl ParseOptions {
+    pub(crate) fn syntax(&self) -> Result<Syntax<'_>, Error<'static>> {
+        for (name, sigil) in [
+            ("timer", self.timer_sigil),
+            ("material", self.material_sigil),
+        ] {
+            if sigil.is_whitespace() || RESERVED.contains(sigil) {
+                return Err(Error::Options(format!(
+                    "the {name} sigil `{sigil}` is already part of the syntax"
+                )));
+            }
+        }
+        if self.timer_sigil == self.material_sigil {
+            return Err(Error::Options(
+                "timers and materials need different sigils".to_string(),
+            ));
+        }
+        for &prefix in self.tags.prefixes() {
+            if prefix.is_whitespace()
+                || RESERVED.contains(prefix)
+                || prefix == self.timer_sigil
+                || prefix == self.material_sigil
+            {
+                return Err(Error::Options(format!(
+                    "the tag prefix `{prefix}` is already part of the syntax"
+                )));
+            }
+        }
+        Ok(Syntax {
+            timer: self.timer_sigil,
+            material: self.material_sigil,
+            tags: self.tags.prefixes(),
+            name_chars: self.name_chars,
+            lenient: self.mode == Mode::Lenient,
+            front_matter: self.front_matter,
+        })
+    }
+}
+
+/// A recipe parsed with [`parse_with`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parsed<'a> {
+    pub tokens: Vec<SpannedToken<'a>>,
+    /// The version the recipe was checked against
+    pub version: SpecVersion,
+    /// Constructs the version doesn't support
+    pub warnings: Vec<Unsupported>,
+}
+
+#[derive(Debug)]
+pub enum Error<'a> {
+    /// The recipe is not valid
+    Syntax(ParseError<LocatingSlice<&'a str>, ContextError>),
+    /// The declared or requested version is not one the parser knows
+    UnknownVersion(String),
+    /// Constructs the version doesn't support, with `deny_unsupported`
+    Unsupported(Vec<Unsupported>),
+    /// The options can't be used together
+    Options(String),
+    /// The recipe is bigger than `max_input_size`
+    TooLarge { size: usize, max: usize },
+    /// The front matter is nested deeper than `max_front_matter_nesting`
+    TooDeep { depth: usize, max: usize },
+}
+
+impl Display for Error<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Syntax(error) => write!(f, "{error}"),
+            Error::UnknownVersion(version) => {
+                let supported: Vec<String> = SpecVersion::SUPPORTED
+                    .iter()
+                    .map(SpecVersion::to_string)
+                    .collect();
+                write!(
+                    f,
+                    "unknown recp-version `{version}`, expected one of {}",
+                    supported.join(", ")
+                )
+            }
+            Error::Unsupported(found) => {
+                let found: Vec<String> = found.iter().map(Unsupported::to_string).collect();
+                write!(f, "{}", found.join("\n"))
+            }
+            Error::Options(message) => write!(f, "{message}"),
+            Error::TooLarge { size, max } => {
+                write!(f, "the recipe has {size} bytes, the maximum is {max}")
+            }
+            Error::TooDeep { depth, max } => write!(
+                f,
+                "the front matter is nested {depth} levels deep, the maximum is {max}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error<'_> {}
+
+impl<'a> From<ParseError<LocatingSlice<&'a str>, ContextError>> for Error<'a> {
+    fn from(error: ParseError<LocatingSlice<&'a str>, ContextError>) -> Self {
+        Error::Syntax(error)
+    }
+}
+
+/// The text of a front matter line that can open lists and tables, without its
+/// comment, quoted strings and the brackets inside other text. Quotes and brackets
+/// only count at the start of a value or inside brackets, `brackets` deep from the
+/// previous lines, so the apostrophe of `grandma's soup [mild]` doesn't.
+fn structure(line: &str, mut brackets: usize) -> String {
+    let mut out = String::new();
+    let mut chars = line.chars().peekable();
+    let mut value_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => break,
+            '"' | '\'' if value_start => {
+                while let Some(s) = chars.next() {
+                    match s {
+                        '\\' if c == '"' => {
+                            chars.next();
+                        }
+                        // `''` is a quote in a single quoted string
+                        '\'' if c == '\'' && chars.peek() == Some(&'\'') => {
+                            chars.next();
+                        }
+                        s if s == c => break,
+                        _ => {}
+                    }
+                }
+                value_start = false;
+            }
+            c if c.is_whitespace() => out.push(c),
+            '[' | '{' if !value_start && brackets == 0 => value_start = false,
+            ']' | '}' if brackets == 0 => value_start = false,
+            c => {
+                match c {
+                    '[' | '{' => brackets += 1,
+                    ']' | '}' => brackets -= 1,
+                    _ => {}
+                }
+                value_start = matches!(c, ':' | '=' | ',' | '[' | '{' | '-');
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+/// Levels of lists and tables in a front matter, counted by the brackets and, in
+/// YAML, by the indentation and the dashes of list items. Quoted strings and
+/// comments are left out, but multiline strings are not, so it can be more than
+/// the real nesting.
+fn nesting(format: FrontMatterFormat, content: &str) -> usize {
+    let mut deepest = 0;
+    let mut brackets = 0usize;
+    let mut indents: Vec<usize> = vec![];
+    for line in content.lines() {
+        let line = structure(line, brackets);
+        let mut rest = line.trim_start();
+        if rest.is_empty() || rest.starts_with('#') {
+            continue;
+        }
+        if format == FrontMatterFormat::Yaml && brackets == 0 {
+            let mut column = line.len() - rest.len();
+            loop {
+                while indents.last().is_some_and(|last| *last >= column) {
+                    indents.pop();
+                }
+                match rest.strip_prefix('-') {
+                    Some(item) if item.is_empty() || item.starts_with([' ', '\t']) => {
+                        indents.push(column);
+                        let value = item.trim_start();
+                        column += rest.len() - value.len();
+                        rest = value;
+                    }
+                    _ => {
+                        if rest.contains(": ") || rest.ends_with(':') {
+                            indents.push(column);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        let levels = indents.len().max(1);
+        for c in rest.chars() {
+            match c {
+                '[' | '{' => {
+                    brackets += 1;
+                    deepest = deepest.max(levels + brackets);
+                }
+                ']' | '}' => brackets = brackets.saturating_sub(1),
+                _ => {}
+            }
+        }
+        deepest = deepest.max(levels);
+    }
+    deepest
+}
+
+/// Remove the comments and join the spaces, as the options say
+pub(crate) fn tidy<'a>(
+    input: &'a str,
+    tokens: Vec<SpannedToken<'a>>,
+    options: &ParseOptions,
+) -> Vec<SpannedToken<'a>> {
+    if options.keep_comments && !options.merge_whitespace {
+        return tokens;
+    }
+    let mut result: Vec<SpannedToken<'a>> = Vec::with_capacity(tokens.len());
+    for (token, span) in tokens {
+        match (token, result.last_mut()) {
+            (Token::Comment(_), _) if !options.keep_comments => {}
+            (Token::Space(_), Some((last @ Token::Space(_), last_span)))
+                if options.merge_whitespace && last_span.end == span.start =>
+            {
+                last_span.end = span.end;
+                *last = Token::Space(&input[last_span.clone()]);
+            }
+            (token, _) => result.push((token, span)),
+        }
+    }
+    result
+}
+
+/// Parse a recipe with options, and check it against a version of the spec.
+///
+/// ```
+/// use recipe_parser::{ParseOptions, SpecVersion, parse_with};
+///
+/// let parsed = parse_with(">> recp-version: 0.2\n== Base ==", &ParseOptions::default()).unwrap();
+/// assert_eq!(parsed.version, SpecVersion::V0_2);
+/// assert_eq!(parsed.warnings[0].to_string(), "a section needs recp-version 0.3");
+/// ```
+pub fn parse_with<'a>(input: &'a str, options: &ParseOptions) -> Result<Parsed<'a>, Error<'a>> {
+    let syntax = options.syntax()?;
+    if let Some(max) = options.max_input_size.filter(|max| input.len() > *max) {
+        return Err(Error::TooLarge {
+            size: input.len(),
+            max,
+        });
+    }
+    if let Some(max) = options
+        .max_front_matter_nesting
+        .filter(|_| options.front_matter)
+    {
+        let mut stream = LocatingSlice::new(input);
+        if let Ok(Token::FrontMatter { format, content }) = front_matter(&mut stream) {
+            let depth = nesting(format, content);
+            if depth > max {
+                return Err(Error::TooDeep { depth, max });
+            }
+        }
+    }
+    let tokens = recipe_with_spans(syntax).parse(LocatingSlice::new(input))?;
+    let version = match (options.version, declared_version(&tokens)) {
+        (Some(version), _) => version,
+        (None, Some(declared)) => declared
+            .parse()
+            .map_err(|_| Error::UnknownVersion(declared.to_string()))?,
+        (None, None) => SPEC_VERSION,
+    };
+    if !version.is_supported() {
+        return Err(Error::UnknownVersion(version.to_string()));
+    }
+    let warnings = unsupported(&tokens, version);
+    if options.deny_unsupported && !warnings.is_empty() {
+        return Err(Error::Unsupported(warnings));
+    }
+    Ok(Parsed {
+        tokens: tidy(input, tokens, options),
+        version,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Amount, parse, serialize};
+    use rstest::*;
+
+    #[test]
+    fn test_parse_with_latest_version() {
+        let parsed = parse_with("== Base ==\n{salt}?()", &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.version, SPEC_VERSION);
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_with_declared_version() {
+        let input = ">> recp-version: 0.2\nAdd {salt}(1 pinch, ground)";
+        let parsed = parse_with(input, &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.version, SpecVersion::V0_2);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert_eq!(
+            &input[parsed.warnings[0].span.clone()],
+            "{salt}(1 pinch, ground)"
+        );
+    }
+
+    #[test]
+    fn test_parse_with_requested_version_overrides_declared() {
+        let options = ParseOptions {
+            version: Some(SpecVersion::V0_3),
+            ..Default::default()
+        };
+        let parsed = parse_with(">> recp-version: 0.2\n== Base ==", &options).unwrap();
+        assert_eq!(parsed.version, SpecVersion::V0_3);
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_with_deny_unsupported() {
+        let options = ParseOptions {
+            version: Some(SpecVersion::V0_2),
+            deny_unsupported: true,
+            ..Default::default()
+        };
+        let error = parse_with("== Base ==\nBoil {water}", &options).unwrap_err();
+        assert_eq!(error.to_string(), "a section needs recp-version 0.3");
+        assert!(parse_with("Boil {water}", &options).is_ok());
+    }
+
+    #[test]
+    fn test_parse_with_unknown_version() {
+        let error = parse_with(">> recp-version: 9.1", &ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown recp-version `9.1`, expected one of 0.2, 0.3, 0.4"
+        );
+        let error = parse_with(">> recp-version: soon", &ParseOptions::default()).unwrap_err();
+        assert!(matches!(error, Error::UnknownVersion(version) if version == "soon"));
+    }
+
+    fn tokens<'a>(input: &'a str, options: &ParseOptions) -> Vec<Token<'a>> {
+        let parsed = parse_with(input, options).unwrap();
+        parsed.tokens.into_iter().map(|(token, _)| token).collect()
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        let options = ParseOptions {
+            front_matter: false,
+            ..Default::default()
+        };
+        let tokens = parse_with("---\nname: soup\n---\nBoil", &options)
+            .unwrap()
+            .tokens;
+        assert_eq!(tokens[0].0, Token::Word("---"));
+        assert_eq!(
+            tokens.last().map(|(token, _)| token),
+            Some(&Token::Backstory("Boil"))
+        );
+    }
+
+    #[test]
+    fn test_parse_with_lenient_mode() {
+        let input = "Boil {water for t{5";
+        assert!(parse_with(input, &ParseOptions::default()).is_err());
+        let options = ParseOptions {
+            mode: Mode::Lenient,
+            ..Default::default()
+        };
+        assert_eq!(
+            tokens(input, &options),
+            vec![
+                Token::Word("Boil"),
+                Token::Space(" "),
+                Token::Word("{water"),
+                Token::Space(" "),
+                Token::Word("for"),
+                Token::Space(" "),
+                Token::Word("t"),
+                Token::Word("{5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_without_comments() {
+        let options = ParseOptions {
+            keep_comments: false,
+            ..Default::default()
+        };
+        let parsed = parse_with("Boil /* hot */ water", &options).unwrap();
+        assert_eq!(
+            parsed.tokens,
+            vec![
+                (Token::Word("Boil"), 0..4),
+                (Token::Space(" "), 4..5),
+                (Token::Word("water"), 15..20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_merged_whitespace() {
+        let options = ParseOptions {
+            merge_whitespace: true,
+            ..Default::default()
+        };
+        let parsed = parse_with("Boil \n  water", &options).unwrap();
+        assert_eq!(
+            parsed.tokens,
+            vec![
+                (Token::Word("Boil"), 0..4),
+                (Token::Space(" \n  "), 4..8),
+                (Token::Word("water"), 8..13),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_custom_sigils() {
+        let options = ParseOptions {
+            timer_sigil: '~',
+            material_sigil: '%',
+            ..Default::default()
+        };
+        assert_eq!(
+            tokens("Wait~{5 min} in %{pot} t{5}", &options),
+            vec![
+                Token::Word("Wait"),
+                Token::Timer("5 min"),
+                Token::Space(" "),
+                Token::Word("in"),
+                Token::Space(" "),
+                Token::Material("pot"),
+                Token::Space(" "),
+                Token::Word("t"),
+                Token::Ingredient {
+                    name: "5",
+                    quantity: None,
+                    unit: None,
+                    alternatives: vec![],
+                    note: None,
+                    optional: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_invalid_sigils() {
+        let options = ParseOptions {
+            timer_sigil: '{',
+            ..Default::default()
+        };
+        let error = parse_with("Boil", &options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the timer sigil `{` is already part of the syntax"
+        );
+        let options = ParseOptions {
+            material_sigil: 't',
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_with("Boil", &options),
+            Err(Error::Options(_))
+        ));
+        // the mark of optional ingredients, `{salt}?{x}` would be ambiguous
+        let options = ParseOptions {
+            timer_sigil: '?',
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with("{salt}?{x}", &options).unwrap_err().to_string(),
+            "the timer sigil `?` is already part of the syntax"
+        );
+    }
+
+    #[test]
+    fn test_parse_with_tags() {
+        let options = ParseOptions {
+            tags: TagRegistry::from_iter(['%', '#']),
+            ..Default::default()
+        };
+        let input = "Bake in %{oven 180C} for #{batch}(2 trays), a+{b}";
+        let parsed = parse_with(input, &options).unwrap();
+        let tags: Vec<_> = parsed
+            .tokens
+            .iter()
+            .filter(|(token, _)| matches!(token, Token::Tag { .. }))
+            .map(|(token, span)| (token.clone(), &input[span.clone()]))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                (
+                    Token::Tag {
+                        prefix: "%",
+                        body: "oven 180C",
+                        amount: None,
+                    },
+                    "%{oven 180C}"
+                ),
+                (
+                    Token::Tag {
+                        prefix: "#",
+                        body: "batch",
+                        amount: Some(Amount {
+                            quantity: Some("2"),
+                            unit: Some("trays"),
+                        }),
+                    },
+                    "#{batch}(2 trays)"
+                ),
+            ]
+        );
+        assert_eq!(serialize(&tokens(input, &options)), input);
+    }
+
+    #[test]
+    fn test_parse_with_unregistered_tags() {
+        let options = ParseOptions {
+            tags: TagRegistry::from_iter(['%']),
+            ..Default::default()
+        };
+        let expected = vec![
+            Token::Word("#"),
+            Token::Ingredient {
+                name: "note",
+                quantity: None,
+                unit: None,
+                alternatives: vec![],
+                note: None,
+                optional: false,
+            },
+        ];
+        assert_eq!(tokens("#{note}", &options), expected);
+        assert_eq!(parse("#{note}").unwrap(), expected);
+        assert_eq!(
+            parse("%{oven}").unwrap()[0],
+            Token::Word("%"),
+            "the prefix is a word when it's not registered"
+        );
+    }
+
+    #[test]
+    fn test_parse_with_invalid_tags() {
+        let options = ParseOptions {
+            tags: TagRegistry::from_iter(['&']),
+            ..Default::default()
+        };
+        let error = parse_with("Boil", &options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the tag prefix `&` is already part of the syntax"
+        );
+        let options = ParseOptions {
+            tags: TagRegistry::from_iter(['%']),
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_with("Bake %{oven", &options),
+            Err(Error::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_with_name_chars() {
+        let mut options = ParseOptions {
+            name_chars: Some(|c| c.is_alphabetic() || c == ' '),
+            ..Default::default()
+        };
+        assert!(parse_with("Add {sea salt} to a &{pot}", &options).is_ok());
+        let Error::Syntax(error) = parse_with("Add {salt 2}", &options).unwrap_err() else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(error.offset(), 5);
+
+        options.mode = Mode::Lenient;
+        assert_eq!(
+            tokens("{salt 2}", &options),
+            vec![Token::Word("{salt"), Token::Space(" "), Token::Word("2}")]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_max_input_size() {
+        let options = ParseOptions {
+            max_input_size: Some(8),
+            ..Default::default()
+        };
+        assert!(parse_with("Boil egg", &options).is_ok());
+        let error = parse_with("Boil eggs", &options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the recipe has 9 bytes, the maximum is 8"
+        );
+    }
+
+    #[test]
+    fn test_parse_with_max_front_matter_nesting() {
+        let options = ParseOptions {
+            max_front_matter_nesting: Some(2),
+            ..Default::default()
+        };
+        assert!(parse_with("---\ntags: [vegan]\n---\nBoil", &options).is_ok());
+        let error = parse_with("---\ntags: [[vegan]]\n---\nBoil", &options).unwrap_err();
+        assert!(matches!(error, Error::TooDeep { depth: 3, max: 2 }));
+        // checked before the rest of the recipe
+        let error = parse_with("---\ntags: [[vegan]]\n---\nBoil {water", &options).unwrap_err();
+        assert!(matches!(error, Error::TooDeep { .. }));
+    }
+
+    #[rstest]
+    #[case(FrontMatterFormat::Yaml, "name: soup", 1)]
+    #[case(FrontMatterFormat::Yaml, "tags: [vegan, {a: 1}]", 3)]
+    #[case(FrontMatterFormat::Yaml, "a:\n  b:\n    c: 1\nd: 2", 3)]
+    #[case(FrontMatterFormat::Yaml, "- - - a", 3)]
+    #[case(
+        FrontMatterFormat::Yaml,
+        "steps:\n  - name: boil\n    time: 5\n  - fry",
+        3
+    )]
+    #[case(FrontMatterFormat::Toml, "name = 'soup'\n[nutrition]\nfat = 1", 2)]
+    #[case(FrontMatterFormat::Toml, "a = [[[1]]]", 4)]
+    #[case(FrontMatterFormat::Yaml, "name: \"[[[soup\" # {{{", 1)]
+    #[case(FrontMatterFormat::Yaml, "name: 'it''s [[soup'", 1)]
+    #[case(FrontMatterFormat::Yaml, "name: grandma's [soup]", 1)]
+    #[case(FrontMatterFormat::Yaml, "tags: [a,\n  [b]]", 3)]
+    #[case(FrontMatterFormat::Yaml, "tags: ['[a', \"]b\", [c]]", 3)]
+    #[case(FrontMatterFormat::Toml, "name = \"{{soup\\\" [\"", 1)]
+    fn test_nesting(
+        #[case] format: FrontMatterFormat,
+        #[case] content: &str,
+        #[case] expected: usize,
+    ) {
+        assert_eq!(nesting(format, content), expected);
+    }
+
+    #[test]
+    fn test_parse_with_syntax_error() {
+        let error = parse_with("Boil {water", &ParseOptions::default()).unwrap_err();
+        assert!(matches!(error, Error::Syntax(_)));
+    }
+}
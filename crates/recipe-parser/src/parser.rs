@@ -1,22 +1,84 @@
 use std::fmt::Display;
-use winnow::ascii::{line_ending, multispace0, multispace1, space0, space1};
-use winnow::combinator::{alt, cut_err, delimited, not, opt, preceded, repeat};
-use winnow::error::{ContextError, ParseError, StrContext, StrContextValue};
-use winnow::token::{any, rest, take_until, take_while};
+use std::ops::Range;
+
+use crate::escape::{is_escapable, unescape};
+use winnow::ascii::{Caseless, line_ending, multispace0, multispace1, space0, space1};
+use winnow::combinator::{
+    alt, cut_err, delimited, dispatch, eof, fail, not, opt, peek, preceded, repeat, terminated,
+};
+use winnow::error::{ContextError, ErrMode, ParseError, StrContext, StrContextValue};
+use winnow::stream::{Location, Stream};
+use winnow::token::{any, none_of, one_of, rest, take_till, take_until, take_while};
 use winnow::{LocatingSlice, ModalResult, Parser};
 
 type Input<'a> = LocatingSlice<&'a str>;
 
-/// Parses a valid string from the input.
+/// A token and the byte range of the input it was parsed from
+pub type SpannedToken<'a> = (Token<'a>, Range<usize>);
+
+/// The parts of the syntax that can be changed with the parse options
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Syntax<'s> {
+    pub timer: char,
+    pub material: char,
+    /// Prefixes of the tags registered by the application
+    pub tags: &'s [char],
+    /// Characters allowed in the names inside curly braces, any when not set
+    pub name_chars: Option<fn(char) -> bool>,
+    /// Invalid tags are words instead of an error
+    pub lenient: bool,
+    /// A `---` or `+++` block at the beginning is a front matter
+    pub front_matter: bool,
+}
+
+impl Default for Syntax<'_> {
+    fn default() -> Self {
+        Syntax {
+            timer: 't',
+            material: '&',
+            tags: &[],
+            name_chars: None,
+            lenient: false,
+            front_matter: true,
+        }
+    }
+}
+
+impl Syntax<'_> {
+    fn is_name(&self, name: &str) -> bool {
+        self.name_chars
+            .is_none_or(|allowed| unescape(name).chars().all(allowed))
+    }
+
+    fn is_sigil(&self, c: char) -> bool {
+        c == self.timer || c == self.material || c == '@' || c == '°' || self.is_tag(c)
+    }
+
+    fn is_tag(&self, c: char) -> bool {
+        self.tags.contains(&c)
+    }
+}
+
+/// Parse a backslash followed by an escapable character, like `\{` or `\}`
+fn parse_escaped<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+    ('\\', one_of(is_escapable)).take().parse_next(input)
+}
+
+/// Parses the content of the curly braces.
 ///
-/// This function takes a mutable reference to a string slice and parses a valid string from it.
-/// A valid string can contain alphanumeric characters as well as certain symbols and spaces.
-/// The function returns a `PResult` containing the parsed valid string.
-fn parse_valid_string<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
-    let spaces_and_symbols = "\t /-_@.,%#'";
-    take_while(1.., move |c: char| {
-        c.is_alphanumeric() || spaces_and_symbols.contains(c)
-    })
+/// A name can contain any utf-8 character except line breaks and unescaped
+/// curly braces, e.g: `crème fraîche (full fat)` or `salt \& pepper`.
+fn parse_name<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+    repeat(
+        1..,
+        alt((
+            parse_escaped,
+            take_while(1.., |c: char| !matches!(c, '{' | '}' | '\\' | '\n' | '\r')),
+            "\\",
+        )),
+    )
+    .map(|()| ())
+    .take()
     .parse_next(input)
 }
 
@@ -41,15 +103,28 @@ fn parse_comment<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
 /// ```recp
 /// {salt}
 /// {tomatoes}
+/// {crème fraîche (full fat)}
 /// ```
-fn parse_curly<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
-    delimited(
-        "{",
-        parse_valid_string.map(|v| v.trim()),
-        cut_err("}").context(StrContext::Expected(StrContextValue::CharLiteral('}'))),
-        // "}"
-    )
-    .parse_next(input)
+///
+/// A name with characters the syntax doesn't allow is an error.
+fn parse_curly<'a>(syntax: Syntax<'_>) -> impl Parser<Input<'a>, &'a str, ErrMode<ContextError>> {
+    move |input: &mut Input<'a>| {
+        "{".parse_next(input)?;
+        let start = input.checkpoint();
+        let name = parse_name.map(|v| v.trim()).parse_next(input)?;
+        if !syntax.is_name(name) {
+            input.reset(&start);
+            return cut_err(fail)
+                .context(StrContext::Expected(StrContextValue::Description(
+                    "a name with only the allowed characters",
+                )))
+                .parse_next(input);
+        }
+        cut_err("}")
+            .context(StrContext::Expected(StrContextValue::CharLiteral('}')))
+            .parse_next(input)?;
+        Ok(name)
+    }
 }
 
 /// The amount of an ingredient must be numeric
@@ -66,17 +141,26 @@ fn parse_quantity<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
     let spaces_and_symbols = ".,/_";
 
     cut_err(
-        take_while(1.., move |c: char| {
-            c.is_numeric() || spaces_and_symbols.contains(c)
-        })
+        repeat(
+            1..,
+            alt((
+                take_while(1.., move |c: char| {
+                    c.is_numeric() || (c != ',' && spaces_and_symbols.contains(c))
+                }),
+                // a comma followed by a space starts the note of the ingredient
+                terminated(",", not(one_of([' ', '\t']))),
+            )),
+        )
+        .map(|()| ())
+        .take()
         .verify(|s: &str| {
             // NEXT: Can this be improved?
             let has_repeated_symbols = s
                 .as_bytes()
                 .windows(2)
                 .any(|v| v[0] == v[1] && spaces_and_symbols.contains(char::from(v[0])));
-            let last_char = &s[s.len() - 1..];
-            !spaces_and_symbols.contains(last_char) && !has_repeated_symbols
+            let ends_with_symbol = s.ends_with(|c| spaces_and_symbols.contains(c));
+            !ends_with_symbol && !has_repeated_symbols
         }),
     )
     .context(StrContext::Expected(StrContextValue::Description(
@@ -86,37 +170,145 @@ fn parse_quantity<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
 }
 
 /// Parse units like kg, kilograms, pinch, etc.
+///
+/// A unit can contain alphanumeric characters as well as certain symbols and spaces.
+/// Commas are not allowed, because they separate the unit from the note.
 fn parse_unit<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
-    parse_valid_string.parse_next(input)
+    let spaces_and_symbols = "\t /-_@.%#'";
+    take_while(1.., move |c: char| {
+        c.is_alphanumeric() || spaces_and_symbols.contains(c)
+    })
+    .parse_next(input)
+}
+
+/// Quantity and unit of an ingredient
+type QuantityUnit<'a> = (Option<&'a str>, Option<&'a str>);
+
+/// Everything inside the parenthesis after an ingredient
+#[derive(Debug, Default, PartialEq)]
+struct IngredientAmount<'a> {
+    quantity: Option<&'a str>,
+    unit: Option<&'a str>,
+    alternatives: Vec<Amount<'a>>,
+    note: Option<&'a str>,
+}
+
+fn parse_amount<'a>(input: &mut Input<'a>) -> ModalResult<QuantityUnit<'a>> {
+    (
+        opt(parse_quantity),
+        opt(preceded(space0, parse_unit.map(|v| v.trim()))),
+    )
+        .parse_next(input)
 }
 
 /// Ingredient amounts are surrounded by parenthesis
-fn parse_ingredient_amount<'a>(
-    input: &mut Input<'a>,
-) -> ModalResult<(Option<&'a str>, Option<&'a str>)> {
+fn parse_ingredient_amount<'a>(input: &mut Input<'a>) -> ModalResult<QuantityUnit<'a>> {
     delimited(
         ("(", space0),
-        (
-            opt(parse_quantity),
-            opt(preceded(space0, parse_unit.map(|v| v.trim()))),
-        ),
+        parse_amount,
         cut_err(")").context(StrContext::Expected(StrContextValue::CharLiteral(')'))), // cut_err(")"),
     )
     // .context(StrContext::Expected(StrContextValue::CharLiteral('}')))
     .parse_next(input)
 }
 
+/// The preparation note goes after the amount, separated by a comma
+///
+/// ```recp
+/// , finely chopped
+/// ```
+fn parse_note<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+    preceded(
+        (",", space0),
+        repeat(
+            1..,
+            alt((
+                parse_escaped,
+                take_while(1.., |c: char| !matches!(c, '(' | ')' | '\\' | '\n' | '\r')),
+                "\\",
+            )),
+        )
+        .map(|()| ())
+        .take()
+        .map(|v: &str| v.trim()),
+    )
+    .parse_next(input)
+}
+
+/// Alternative amounts are separated by a pipe `|`
+///
+/// ```recp
+/// | 1 can
+/// ```
+fn parse_alternative_amount<'a>(input: &mut Input<'a>) -> ModalResult<Amount<'a>> {
+    preceded((space0, "|", space0), parse_amount)
+        .map(|(quantity, unit)| Amount { quantity, unit })
+        .parse_next(input)
+}
+
+/// Ingredient amounts can have alternatives and a preparation note
+///
+/// ```recp
+/// (1, finely chopped)
+/// (200 gr, rinsed)
+/// (, finely chopped)
+/// (400 gr | 1 can)
+/// (400 gr | 1 can, drained)
+/// ```
+fn parse_ingredient_amount_with_note<'a>(
+    input: &mut Input<'a>,
+) -> ModalResult<IngredientAmount<'a>> {
+    delimited(
+        ("(", space0),
+        alt((
+            parse_note.map(|note| IngredientAmount {
+                note: Some(note),
+                ..Default::default()
+            }),
+            (
+                parse_amount,
+                repeat(0.., parse_alternative_amount),
+                opt(parse_note),
+            )
+                .map(|((quantity, unit), alternatives, note)| IngredientAmount {
+                    quantity,
+                    unit,
+                    alternatives,
+                    note,
+                }),
+        )),
+        cut_err(")").context(StrContext::Expected(StrContextValue::CharLiteral(')'))),
+    )
+    .parse_next(input)
+}
+
 /// Ingredients come in these formats:
 ///
 /// ```recp
 /// {quinoa}(200gr)
 /// {tomatoes}(2)
 /// {sweet potatoes}(2)
+/// {onion}(1, finely chopped)
+/// {salt}?(1 pinch)
+/// {chickpeas}(400 gr | 1 can)
 /// ```
+///
+/// The `?` of an optional ingredient goes right before the amount, which can be
+/// empty, like `{salt}?()`, so in `Did you add the {salt}?` it's prose.
 fn parse_ingredient<'a>(
-    input: &mut Input<'a>,
-) -> ModalResult<(&'a str, Option<(Option<&'a str>, Option<&'a str>)>)> {
-    (parse_curly, opt(parse_ingredient_amount)).parse_next(input)
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, (&'a str, bool, Option<IngredientAmount<'a>>), ErrMode<ContextError>> {
+    (
+        parse_curly(syntax),
+        alt((
+            ("?(", space0, ")").map(|_| (true, None)),
+            (
+                opt(terminated("?", peek("("))).map(|v| v.is_some()),
+                opt(parse_ingredient_amount_with_note),
+            ),
+        )),
+    )
+        .map(|(name, (optional, amount))| (name, optional, amount))
 }
 
 /// Materials format:
@@ -126,18 +318,20 @@ fn parse_ingredient<'a>(
 /// &{small jar}
 /// &{stick}
 /// ```
-fn parse_material<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
-    preceded("&", parse_curly).parse_next(input)
+fn parse_material<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, &'a str, ErrMode<ContextError>> {
+    preceded(syntax.material, parse_curly(syntax))
 }
 
-/// Materials format:
+/// Timers format:
 ///
 /// ```recp
 /// t{25 minutes}
 /// t{10 sec}
 /// ```
-fn parse_timer<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
-    preceded("t", parse_curly).parse_next(input)
+fn parse_timer<'a>(syntax: Syntax<'_>) -> impl Parser<Input<'a>, &'a str, ErrMode<ContextError>> {
+    preceded(syntax.timer, parse_curly(syntax))
 }
 
 /// Parse a reference to another recipe
@@ -147,37 +341,177 @@ fn parse_timer<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
 /// @{woile/special-tomato-sauce}(100 ml)
 /// ```
 fn parse_recipe_ref<'a>(
-    input: &mut Input<'a>,
-) -> ModalResult<(&'a str, Option<(Option<&'a str>, Option<&'a str>)>)> {
-    preceded("@", (parse_curly, opt(parse_ingredient_amount))).parse_next(input)
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, (&'a str, Option<QuantityUnit<'a>>), ErrMode<ContextError>> {
+    preceded("@", (parse_curly(syntax), opt(parse_ingredient_amount)))
 }
 
-fn parse_word<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
-    repeat(
-        1..,
-        (
-            not(alt((
-                " ", "\t", "\r", "\n", "'", "`", "{", "t{", "&{", "@{",
-            ))),
-            any,
-        ),
+/// Degrees of a temperature, they can be negative.
+///
+/// Unlike in an amount, a value that is not a quantity backtracks.
+fn parse_degrees<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+    (opt("-"), parse_quantity)
+        .take()
+        .parse_next(input)
+        .map_err(ErrMode::backtrack)
+}
+
+/// Temperatures have a value and a scale, or are a gas mark:
+///
+/// ```recp
+/// °{180 C}
+/// °{350 °F}
+/// °{-18 celsius}
+/// °{gas mark 4}
+/// ```
+///
+/// Anything else in the curly braces is not a temperature, like `°{180}`, and
+/// the parser backtracks. Only a temperature without the closing brace fails.
+fn parse_temperature<'a>(input: &mut Input<'a>) -> ModalResult<(&'a str, TemperatureScale)> {
+    let scale = take_while(1.., |c: char| !matches!(c, '}' | '\n' | '\r'))
+        .verify_map(|scale: &str| scale.parse::<TemperatureScale>().ok());
+    delimited(
+        ("°{", space0),
+        alt((
+            preceded((Caseless("gas mark"), space0), parse_degrees)
+                .map(|value| (value, TemperatureScale::GasMark)),
+            (parse_degrees, space0, scale).map(|(value, _, scale)| (value, scale)),
+        )),
+        cut_err((space0, "}")),
     )
-    .map(|()| ())
-    .take()
+    .context(StrContext::Expected(StrContextValue::Description(
+        "a temperature, like 180 C, 350 F or gas mark 4",
+    )))
     .parse_next(input)
 }
 
+/// Parse a tag with a prefix registered by the application, it can have an
+/// amount like a recipe reference
+///
+/// ```recp
+/// %{oven 180C}
+/// #{batch}(2)
+/// ```
+fn parse_tag<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, (&'a str, &'a str, Option<QuantityUnit<'a>>), ErrMode<ContextError>> {
+    (
+        one_of(move |c| syntax.is_tag(c)).take(),
+        parse_curly(syntax),
+        opt(parse_ingredient_amount),
+    )
+}
+
+/// Consumes a word until a space or the beginning of a tag is found.
+///
+/// Escaped characters belong to the word, so `t\{` is not a timer.
+///
+/// Words are most of a recipe, so the bytes are scanned directly instead of
+/// trying each delimiter at every character. All the delimiters are ASCII, so
+/// the word always ends at a character boundary. A sigil right before a curly
+/// brace starts a tag, so it's left out of the word.
+fn parse_word<'a>(syntax: Syntax<'_>) -> impl Parser<Input<'a>, &'a str, ErrMode<ContextError>> {
+    move |input: &mut Input<'a>| {
+        let bytes = input.as_bytes();
+        let mut end = 0;
+        let mut escaped = 0;
+        while let Some(&byte) = bytes.get(end) {
+            let next = bytes.get(end + 1).copied();
+            match byte {
+                b'\\' if next.is_some_and(|c| is_escapable(char::from(c))) => {
+                    end += 2;
+                    escaped = end;
+                }
+                b' ' | b'\t' | b'\r' | b'\n' | b'\'' | b'`' | b'{' => break,
+                _ => end += 1,
+            }
+        }
+        if bytes.get(end) == Some(&b'{') && end > escaped {
+            let sigil = input[..end]
+                .chars()
+                .next_back()
+                .filter(|c| syntax.is_sigil(*c));
+            end -= sigil.map_or(0, char::len_utf8);
+        }
+        if end == 0 {
+            return fail.parse_next(input);
+        }
+        Ok(input.next_slice(end))
+    }
+}
+
+/// The value of a metadata goes until the end of the line.
+///
+/// Following lines that are indented continue the value,
+/// the indentation is kept in the raw value.
+///
+/// ```recp
+/// >> description: a long description
+///    that continues here
+/// ```
+fn parse_metadata_value<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+    (
+        take_till(0.., '\n'),
+        repeat(0.., ("\n", space1, take_till(1.., '\n')).void()).map(|()| ()),
+    )
+        .take()
+        .parse_next(input)
+}
+
+/// Metadata goes in its own line, it can be the last line of the recipe
+///
+/// ```recp
+/// >> name: hummus
+/// >> tags: vegan
+/// ```
 fn parse_metadata<'a>(input: &mut Input<'a>) -> ModalResult<(&'a str, &'a str)> {
     preceded(
         (">>", space0),
         (
-            take_while(1.., |c| c != ':'),
-            preceded((":", space0), take_until(0.., "\n")),
+            take_while(1.., |c| !matches!(c, ':' | '\n')),
+            preceded((":", space0), parse_metadata_value),
         ),
     )
     .parse_next(input)
 }
 
+/// The input is at the beginning of the recipe or of a line
+fn at_line_start(input: &Input<'_>) -> bool {
+    let offset = input.current_token_start();
+    let mut recipe = *input;
+    recipe.reset_to_start();
+    offset == 0 || recipe[..offset].ends_with('\n')
+}
+
+/// A section groups the lines that follow it, till the next section.
+/// It goes in its own line:
+///
+/// ```recp
+/// == Filling ==
+/// ```
+fn parse_section<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+    if !at_line_start(input) {
+        return fail.parse_next(input);
+    }
+    delimited(
+        ("==", space0),
+        repeat(
+            1..,
+            alt((
+                parse_escaped,
+                (not("=="), none_of(['\\', '\n', '\r'])).take(),
+                "\\",
+            )),
+        )
+        .map(|()| ())
+        .take()
+        .map(str::trim_end)
+        .verify(|name: &str| !name.is_empty()),
+        ("==", space0, peek(alt((line_ending, eof)))),
+    )
+    .parse_next(input)
+}
+
 /// The backstory is separated by `---`, and it consumes till the end
 /// ```recp
 /// my recipe bla with {ingredient1}
@@ -196,6 +530,71 @@ fn parse_backstory<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
     .parse_next(input)
 }
 
+/// The front matter is a block at the beginning of the recipe,
+/// fenced by `---` for YAML or `+++` for TOML.
+///
+/// ```recp
+/// ---
+/// name: hummus
+/// tags: [vegan, easy]
+/// ---
+/// ```
+///
+/// If the closing fence is missing, or the block doesn't start with a key, it's not
+/// a front matter.
+fn parse_front_matter<'a>(input: &mut Input<'a>) -> ModalResult<(FrontMatterFormat, &'a str)> {
+    let (fence, format) = alt((
+        "---".value(FrontMatterFormat::Yaml),
+        "+++".value(FrontMatterFormat::Toml),
+    ))
+    .with_taken()
+    .map(|(format, fence)| (fence, format))
+    .parse_next(input)?;
+    let closing_fence = || (fence, space0, alt((line_ending, eof)));
+    let content = preceded(
+        (space0, line_ending),
+        repeat(
+            0..,
+            (not(closing_fence()), take_till(0.., '\n'), line_ending).void(),
+        )
+        .map(|()| ())
+        .take(),
+    )
+    .parse_next(input)?;
+    closing_fence().parse_next(input)?;
+    let content = content.strip_suffix('\n').unwrap_or(content);
+    let content = content.strip_suffix('\r').unwrap_or(content);
+    if !starts_with_key(format, content) {
+        return fail.parse_next(input);
+    }
+    Ok((format, content))
+}
+
+/// A front matter is empty or its first line, after blank lines and comments, is a
+/// key, so prose between `---` lines is not a front matter
+fn starts_with_key(format: FrontMatterFormat, content: &str) -> bool {
+    let Some(line) = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+    else {
+        return true;
+    };
+    match format {
+        FrontMatterFormat::Yaml => {
+            let key_end = line.char_indices().find(|&(i, c)| {
+                c == ':'
+                    && line[i + 1..]
+                        .chars()
+                        .next()
+                        .is_none_or(|c| c == ' ' || c == '\t')
+            });
+            !line.starts_with('-') && key_end.is_some_and(|(i, _)| i > 0)
+        }
+        FrontMatterFormat::Toml => line.starts_with('[') || line.find('=').is_some_and(|i| i > 0),
+    }
+}
+
 /// Symbols that create conflict when parsing
 ///
 /// If you have a recipe like:
@@ -214,6 +613,93 @@ fn parse_special_symbols<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
 * The main parser
 **************** */
 
+/// Tokens keep the raw text, but serialized values are unescaped
+#[cfg(feature = "serde")]
+fn serialize_unescaped<S: serde::Serializer>(
+    value: &&str,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&unescape(value))
+}
+
+/// An amount of an ingredient, composed by a quantity and a unit
+///
+/// Example
+///
+/// ```recp
+/// {chickpeas}(400 gr | 1 can)
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+pub struct Amount<'a> {
+    pub quantity: Option<&'a str>,
+    pub unit: Option<&'a str>,
+}
+
+/// Language used in the front matter
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+pub enum FrontMatterFormat {
+    /// Fenced by `---`
+    Yaml,
+    /// Fenced by `+++`
+    Toml,
+}
+
+/// Scale of a temperature
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+pub enum TemperatureScale {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+    /// Marks of gas ovens, `gas mark 4` is about 180 °C
+    GasMark,
+}
+
+impl TemperatureScale {
+    /// How the scale is written in a recipe, like `C`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureScale::Celsius => "C",
+            TemperatureScale::Fahrenheit => "F",
+            TemperatureScale::Kelvin => "K",
+            TemperatureScale::GasMark => "gas mark",
+        }
+    }
+}
+
+impl Display for TemperatureScale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl std::str::FromStr for TemperatureScale {
+    type Err = String;
+
+    /// Parse the symbol or the name of a scale, like `C`, `°F`, `kelvin` or `gas mark`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let scale = value.trim().to_lowercase();
+        match scale.trim_start_matches(['°', 'º']) {
+            "c" | "celsius" | "centigrade" => Ok(TemperatureScale::Celsius),
+            "f" | "fahrenheit" => Ok(TemperatureScale::Fahrenheit),
+            "k" | "kelvin" => Ok(TemperatureScale::Kelvin),
+            "gas mark" | "gas" => Ok(TemperatureScale::GasMark),
+            _ => Err(format!(
+                "unknown temperature scale `{}`, expected C, F, K or gas mark",
+                value.trim()
+            )),
+        }
+    }
+}
+
 /// A recipe string is parsed into many of these tokens
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
@@ -228,7 +714,11 @@ pub enum Token<'a> {
     /// Example:
     /// ```recp
     /// >> name: Salad
+    /// >> description: a value can continue
+    ///    in the next lines when they are indented
     /// ```
+    ///
+    /// The value is raw, continuation lines keep their line break and indentation.
     Metadata {
         key: &'a str,
         value: &'a str,
@@ -240,11 +730,21 @@ pub enum Token<'a> {
     ///
     /// ```recp
     /// {tomato}(1 kg)
+    /// {onion}(1, finely chopped)
+    /// {salt}?(1 pinch)
+    /// {chickpeas}(400 gr | 1 can)
     /// ```
     Ingredient {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))]
         name: &'a str,
         quantity: Option<&'a str>,
         unit: Option<&'a str>,
+        /// Other amounts that can be used instead, separated by `|`
+        alternatives: Vec<Amount<'a>>,
+        /// Preparation note, like `finely chopped`
+        note: Option<&'a str>,
+        /// The ingredient is marked with `?` and can be left out
+        optional: bool,
     },
 
     /// Link to another recipe
@@ -255,11 +755,26 @@ pub enum Token<'a> {
     /// @{path/recipe}(30 ml)
     /// ```
     RecipeRef {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))]
         name: &'a str,
         quantity: Option<&'a str>,
         unit: Option<&'a str>,
     },
 
+    /// A temperature, its value is the raw number of degrees, or the number of the
+    /// gas mark
+    ///
+    /// Example
+    ///
+    /// ```recp
+    /// °{180 C}
+    /// °{gas mark 4}
+    /// ```
+    Temperature {
+        value: &'a str,
+        scale: TemperatureScale,
+    },
+
     /// Mark for a timer
     ///
     /// Example
@@ -267,7 +782,7 @@ pub enum Token<'a> {
     /// ```recp
     /// t{25 minutes}
     /// ```
-    Timer(&'a str),
+    Timer(#[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))] &'a str),
 
     /// Mark for a material required
     ///
@@ -276,9 +791,25 @@ pub enum Token<'a> {
     /// ```recp
     /// &{blender}
     /// ```
-    Material(&'a str),
+    Material(#[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))] &'a str),
+
+    /// Mark with a prefix registered by the application, see [`crate::TagRegistry`].
+    /// The prefixes that are not registered are words.
+    ///
+    /// Example
+    ///
+    /// ```recp
+    /// %{oven 180C}
+    /// #{batch}(2)
+    /// ```
+    Tag {
+        prefix: &'a str,
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))]
+        body: &'a str,
+        amount: Option<Amount<'a>>,
+    },
 
-    Word(&'a str),
+    Word(#[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))] &'a str),
     Space(&'a str),
     Comment(&'a str),
 
@@ -292,81 +823,210 @@ pub enum Token<'a> {
     /// shared by my best friend
     /// ```
     Backstory(&'a str),
+
+    /// Heading that groups the following ingredients and steps
+    ///
+    /// Example
+    ///
+    /// ```recp
+    /// == Filling ==
+    /// ```
+    Section(#[cfg_attr(feature = "serde", serde(serialize_with = "serialize_unescaped"))] &'a str),
+
+    /// Metadata block at the beginning of the recipe, in YAML or TOML
+    ///
+    /// Example
+    ///
+    /// ```recp
+    /// ---
+    /// name: hummus
+    /// tags: [vegan, easy]
+    /// ---
+    /// ```
+    FrontMatter {
+        format: FrontMatterFormat,
+        content: &'a str,
+    },
 }
 
 impl Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Token::Ingredient {
-                name,
-                quantity: _,
-                unit: _,
-            } => write!(f, "{}", name),
+            Token::Ingredient { name, .. } => write!(f, "{}", unescape(name)),
             Token::RecipeRef {
                 name,
                 quantity: _,
                 unit: _,
-            } => write!(f, "\"{}\"", name),
-            Token::Backstory(v)
-            | Token::Timer(v)
+            } => write!(f, "\"{}\"", unescape(name)),
+            Token::Timer(v)
             | Token::Material(v)
+            | Token::Tag { body: v, .. }
             | Token::Word(v)
-            | Token::Space(v) => {
+            | Token::Section(v) => {
+                write!(f, "{}", unescape(v))
+            }
+            Token::Temperature {
+                value,
+                scale: TemperatureScale::GasMark,
+            } => write!(f, "gas mark {value}"),
+            Token::Temperature { value, scale } => write!(f, "{value} °{scale}"),
+            Token::Backstory(v) | Token::Space(v) => {
                 write!(f, "{}", v)
             }
             Token::Metadata { key: _, value: _ } => Ok(()),
             Token::Comment(_) => Ok(()),
+            Token::FrontMatter { .. } => Ok(()),
         }
     }
 }
 
-pub fn recipe_value<'a>(input: &mut Input<'a>) -> ModalResult<Token<'a>> {
-    alt((
-        parse_metadata.map(|(key, value)| Token::Metadata { key, value }),
-        parse_material.map(|m| Token::Material(m)),
-        parse_timer.map(|t| Token::Timer(t)),
-        // Because ingredient doesn't have a prefix before the curly braces, e.g: `&{}`
-        // it must always be parsed after timer and material
-        parse_ingredient.map(|(name, amount)| {
-            let mut quantity = None;
-            let mut unit = None;
-            if let Some((_quantity, _unit)) = amount {
-                quantity = _quantity;
-                unit = _unit;
-            };
+fn parse_ingredient_token<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, Token<'a>, ErrMode<ContextError>> {
+    parse_ingredient(syntax).map(|(name, optional, amount)| {
+        let IngredientAmount {
+            quantity,
+            unit,
+            alternatives,
+            note,
+        } = amount.unwrap_or_default();
 
-            Token::Ingredient {
-                name,
-                quantity,
-                unit,
-            }
-        }),
-        parse_recipe_ref.map(|(name, amount)| {
-            let mut quantity = None;
-            let mut unit = None;
-            if let Some((_quantity, _unit)) = amount {
-                quantity = _quantity;
-                unit = _unit;
-            };
+        Token::Ingredient {
+            name,
+            quantity,
+            unit,
+            alternatives,
+            note,
+            optional,
+        }
+    })
+}
 
-            Token::RecipeRef {
-                name,
-                quantity,
-                unit,
+fn parse_recipe_ref_token<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, Token<'a>, ErrMode<ContextError>> {
+    parse_recipe_ref(syntax).map(|(name, amount)| {
+        let mut quantity = None;
+        let mut unit = None;
+        if let Some((_quantity, _unit)) = amount {
+            quantity = _quantity;
+            unit = _unit;
+        };
+
+        Token::RecipeRef {
+            name,
+            quantity,
+            unit,
+        }
+    })
+}
+
+fn parse_tag_token<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, Token<'a>, ErrMode<ContextError>> {
+    parse_tag(syntax).map(|(prefix, body, amount)| Token::Tag {
+        prefix,
+        body,
+        amount: amount.map(|(quantity, unit)| Amount { quantity, unit }),
+    })
+}
+
+/// Parse the next token of a recipe.
+///
+/// Each kind of token starts with its own symbol, so only the parsers that can
+/// match the first character are tried. A word is the fallback for all of them.
+///
+/// With a lenient syntax, a token that can't be parsed is a word, up to where the
+/// next token can start.
+pub fn recipe_value<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, Token<'a>, ErrMode<ContextError>> {
+    move |input: &mut Input<'a>| {
+        let start = input.checkpoint();
+        let word = || parse_word(syntax).map(Token::Word);
+        let token = dispatch! {peek(any);
+            '>' => alt((
+                parse_metadata.map(|(key, value)| Token::Metadata { key, value }),
+                word(),
+            )),
+            '=' => alt((parse_section.map(Token::Section), word())),
+            c if c == syntax.material => alt((parse_material(syntax).map(Token::Material), word())),
+            c if c == syntax.timer => alt((parse_timer(syntax).map(Token::Timer), word())),
+            c if syntax.is_tag(c) => alt((parse_tag_token(syntax), word())),
+            // `°{180}` is not a temperature, the `°` is a word before an ingredient
+            '°' => alt((
+                parse_temperature.map(|(value, scale)| Token::Temperature { value, scale }),
+                word(),
+                "°".map(Token::Word),
+            )),
+            '{' => parse_ingredient_token(syntax),
+            '@' => alt((parse_recipe_ref_token(syntax), word())),
+            '/' => alt((parse_comment.map(Token::Comment), word())),
+            '(' | '\'' | '`' => parse_special_symbols.map(Token::Word),
+            ' ' | '\t' => space1.map(Token::Space),
+            '\n' | '\r' => alt((
+                parse_backstory.map(Token::Backstory),
+                multispace1.map(Token::Space),
+            )),
+            _ => word(),
+        }
+        .parse_next(input);
+        match token {
+            Err(_) if syntax.lenient => {
+                input.reset(&start);
+                (any, opt(parse_word(syntax)))
+                    .take()
+                    .map(Token::Word)
+                    .parse_next(input)
             }
-        }),
-        parse_backstory.map(|v| Token::Backstory(v)),
-        parse_comment.map(|v| Token::Comment(v)),
-        parse_special_symbols.map(|v| Token::Word(v)),
-        parse_word.map(|v| Token::Word(v)),
-        space1.map(|v| Token::Space(v)),
-        multispace1.map(|v| Token::Space(v)),
-    ))
-    .parse_next(input)
+            token => token,
+        }
+    }
 }
 
-pub fn recipe<'a>(input: &mut Input<'a>) -> ModalResult<Vec<Token<'a>>> {
-    repeat(0.., recipe_value).parse_next(input)
+pub(crate) fn front_matter<'a>(input: &mut Input<'a>) -> ModalResult<Token<'a>> {
+    parse_front_matter
+        .map(|(format, content)| Token::FrontMatter { format, content })
+        .parse_next(input)
+}
+
+/// A recipe can start with a front matter, followed by any number of tokens.
+///
+/// Each token comes with its span, the byte range it was parsed from.
+pub fn recipe_with_spans<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, Vec<SpannedToken<'a>>, ErrMode<ContextError>> {
+    move |input: &mut Input<'a>| {
+        let mut front_matter = match syntax.front_matter {
+            true => opt(front_matter.with_span()).parse_next(input)?,
+            false => None,
+        };
+        repeat(0.., recipe_value(syntax).with_span())
+            .fold(move || Vec::from_iter(front_matter.take()), push)
+            .parse_next(input)
+    }
+}
+
+/// A recipe can start with a front matter, followed by any number of tokens
+pub fn recipe<'a>(
+    syntax: Syntax<'_>,
+) -> impl Parser<Input<'a>, Vec<Token<'a>>, ErrMode<ContextError>> {
+    move |input: &mut Input<'a>| {
+        let mut front_matter = match syntax.front_matter {
+            true => opt(front_matter).parse_next(input)?,
+            false => None,
+        };
+        repeat(0.., recipe_value(syntax))
+            .fold(move || Vec::from_iter(front_matter.take()), push)
+            .parse_next(input)
+    }
+}
+
+/// Tokens are collected in a single vector, big recipes spend most of the time
+/// writing them to memory
+fn push<T>(mut tokens: Vec<T>, token: T) -> Vec<T> {
+    tokens.push(token);
+    tokens
 }
 
 /// Parse recipe tokens from a string
@@ -383,7 +1043,26 @@ pub fn recipe<'a>(input: &mut Input<'a>) -> ModalResult<Vec<Token<'a>>> {
 /// ```
 pub fn parse(input: &str) -> Result<Vec<Token<'_>>, ParseError<LocatingSlice<&str>, ContextError>> {
     let input = LocatingSlice::new(input);
-    recipe.parse(input)
+    recipe(Syntax::default()).parse(input)
+}
+
+/// Parse recipe tokens from a string, with the byte range of the input each token
+/// was parsed from.
+///
+/// ```
+/// use recipe_parser::{Token, parse_with_spans};
+///
+/// let input = "Boil {potatoes}";
+/// let tokens = parse_with_spans(input).expect("recipe could not be parsed");
+/// let (token, span) = tokens.last().unwrap();
+/// assert!(matches!(token, Token::Ingredient { .. }));
+/// assert_eq!(&input[span.clone()], "{potatoes}");
+/// ```
+pub fn parse_with_spans(
+    input: &str,
+) -> Result<Vec<SpannedToken<'_>>, ParseError<LocatingSlice<&str>, ContextError>> {
+    let input = LocatingSlice::new(input);
+    recipe_with_spans(Syntax::default()).parse(input)
 }
 
 #[cfg(test)]
@@ -398,15 +1077,16 @@ mod test {
     #[case("1/2 lemon", "1/2 lemon")]
     #[case("my-best-sauce", "my-best-sauce")]
     #[case("1.2", "1.2")]
-    #[case("1,2", "1,2")]
+    #[case("1,2", "1")]
     #[case("1_200", "1_200")]
     #[case("@woile", "@woile")]
     #[case("10%", "10%")]
     #[case("#vegan", "#vegan")]
     #[case("mango's", "mango's")]
-    fn test_parse_valid_string(#[case] input: String, #[case] expected: &str) {
+    #[case("pinch, finely ground", "pinch")]
+    fn test_parse_unit(#[case] input: String, #[case] expected: &str) {
         let mut input = LocatingSlice::new(input.as_str());
-        let valid_str = parse_valid_string(&mut input).unwrap();
+        let valid_str = parse_unit(&mut input).unwrap();
         assert_eq!(valid_str, expected)
     }
 
@@ -437,24 +1117,38 @@ mod test {
     #[case("{smashed potatoes}", "smashed potatoes")]
     #[case("{15 minutes}", "15 minutes")]
     #[case("{   15 minutes  }", "15 minutes")]
+    #[case("{crème fraîche (full fat)}", "crème fraîche (full fat)")]
+    #[case("{salt & pepper!}", "salt & pepper!")]
+    #[case(r"{curly \} brace}", r"curly \} brace")]
+    #[case(r"{back\slash}", r"back\slash")]
     fn test_parse_curly_ok(#[case] input: String, #[case] expected: &str) {
         let mut input = LocatingSlice::new(input.as_str());
-        let content = parse_curly(&mut input).expect("to work");
+        let content = parse_curly(Syntax::default())
+            .parse_next(&mut input)
+            .expect("to work");
         assert_eq!(expected, content);
     }
 
     #[test]
     fn test_parse_curly_wrong() {
         let mut input = LocatingSlice::new("{}");
-        let res = parse_curly(&mut input);
+        let res = parse_curly(Syntax::default()).parse_next(&mut input);
         assert!(res.is_err());
 
         let mut input = LocatingSlice::new("{unclosed");
-        let res = parse_curly(&mut input);
+        let res = parse_curly(Syntax::default()).parse_next(&mut input);
         assert!(res.is_err());
 
         let err = res.unwrap_err();
         assert!(matches!(err, winnow::error::ErrMode::Cut(_)));
+
+        let mut input = LocatingSlice::new("{multi\nline}");
+        let res = parse_curly(Syntax::default()).parse_next(&mut input);
+        assert!(matches!(res, Err(winnow::error::ErrMode::Cut(_))));
+
+        let mut input = LocatingSlice::new(r"{escaped\}");
+        let res = parse_curly(Syntax::default()).parse_next(&mut input);
+        assert!(matches!(res, Err(winnow::error::ErrMode::Cut(_))));
     }
 
     #[rstest]
@@ -465,6 +1159,9 @@ mod test {
     #[case("2.1", "2.1")]
     #[case("1/2", "1/2")]
     #[case(".2", ".2")]
+    #[case("2, chopped", "2")]
+    #[case("½", "½")]
+    #[case("1½", "1½")]
     fn test_parse_quantity_ok(#[case] input: String, #[case] expected: &str) {
         let mut input = LocatingSlice::new(input.as_str());
         let content = parse_quantity(&mut input).expect("to work");
@@ -476,6 +1173,7 @@ mod test {
     #[case("2..0")]
     #[case("2,,0")]
     #[case("2//0")]
+    #[case("2,")]
     fn test_parse_quantity_invalid(#[case] input: String) {
         // TODO: Add verify function to validate the last char
         let mut input = LocatingSlice::new(input.as_str());
@@ -508,8 +1206,7 @@ mod test {
         let res = parse_ingredient_amount(&mut input);
         match res {
             Ok(_) => {
-                // should fail the test
-                assert!(false);
+                panic!("parsing should fail");
             }
             Err(e) => match e {
                 winnow::error::ErrMode::Cut(err) => {
@@ -518,10 +1215,9 @@ mod test {
                         "expected a quantity value, like 3, 1.2, 1/2 or 1_000",
                         err.to_string()
                     );
-                    assert!(true);
                 }
                 _ => {
-                    assert!(false);
+                    panic!("expected a cut error");
                 }
             },
         }
@@ -534,34 +1230,122 @@ mod test {
         let res = parse_ingredient_amount(&mut input);
         match res {
             Ok(_) => {
-                // should fail the test
-                assert!(false);
+                panic!("parsing should fail");
             }
             Err(e) => match e {
                 winnow::error::ErrMode::Cut(err) => {
                     println!("{}", err);
                     assert_eq!("expected `)`", err.to_string());
-                    assert!(true);
                 }
                 _ => {
-                    assert!(false);
+                    panic!("expected a cut error");
                 }
             },
         }
     }
 
     #[rstest]
-    #[case("{sweet potato}(200gr)", "sweet potato", Some((Some("200"),Some("gr"))))]
-    #[case("{sweet potato}", "sweet potato", None)]
+    #[case("{sweet potato}(200gr)", "sweet potato", false, Some((Some("200"), Some("gr"), None)))]
+    #[case("{sweet potato}", "sweet potato", false, None)]
+    #[case("{onion}(1, finely chopped)", "onion", false, Some((Some("1"), None, Some("finely chopped"))))]
+    #[case("{salt}?(1 pinch)", "salt", true, Some((Some("1"), Some("pinch"), None)))]
+    #[case("{salt}?(, to taste)", "salt", true, Some((None, None, Some("to taste"))))]
+    #[case("{salt}?()", "salt", true, None)]
+    #[case("{salt}?( )", "salt", true, None)]
+    #[case("{salt}?", "salt", false, None)]
+    #[case("{salt}? to taste", "salt", false, None)]
+    #[case("{salt}?, {pepper}", "salt", false, None)]
+    #[case("{salt}?!", "salt", false, None)]
+    #[case("{salt}?.", "salt", false, None)]
+    #[case(r"{salt}\?", "salt", false, None)]
     fn test_parse_ingredient_ok(
         #[case] input: String,
         #[case] expected_ingredient: &str,
-        #[case] expected_amount: Option<(Option<&str>, Option<&str>)>,
+        #[case] expected_optional: bool,
+        #[case] expected_amount: Option<(Option<&str>, Option<&str>, Option<&str>)>,
     ) {
         let mut input = LocatingSlice::new(input.as_str());
-        let (ingredient, amount) = parse_ingredient(&mut input).unwrap();
+        let (ingredient, optional, amount) = parse_ingredient(Syntax::default())
+            .parse_next(&mut input)
+            .unwrap();
         assert_eq!(expected_ingredient, ingredient);
-        assert_eq!(expected_amount, amount);
+        assert_eq!(expected_optional, optional);
+        assert_eq!(
+            expected_amount,
+            amount.map(|amount| (amount.quantity, amount.unit, amount.note))
+        );
+    }
+
+    #[rstest]
+    #[case("Did you add the {salt}?!", false, Some(Token::Word("?!")))]
+    #[case("Did you add the {salt}?\".", false, Some(Token::Word("?\".")))]
+    #[case(r"Did you add the {salt}\?", false, Some(Token::Word(r"\?")))]
+    #[case("Did you add the {salt}?", false, Some(Token::Word("?")))]
+    #[case("Did you add the {salt}? Then serve.", false, Some(Token::Word("?")))]
+    fn test_question_mark_after_ingredient(
+        #[case] input: &str,
+        #[case] expected_optional: bool,
+        #[case] expected_next: Option<Token>,
+    ) {
+        let tokens = parse(input).expect("parse failed");
+        let position = tokens
+            .iter()
+            .position(|token| matches!(token, Token::Ingredient { .. }))
+            .unwrap();
+        assert!(matches!(
+            tokens[position],
+            Token::Ingredient { optional, .. } if optional == expected_optional
+        ));
+        assert_eq!(tokens.get(position + 1), expected_next.as_ref());
+    }
+
+    #[rstest]
+    #[case("(1, finely chopped)", (Some("1"), None, Some("finely chopped")))]
+    #[case("(200 gr, rinsed)", (Some("200"), Some("gr"), Some("rinsed")))]
+    #[case("(1,5 kg, peeled, in cubes)", (Some("1,5"), Some("kg"), Some("peeled, in cubes")))]
+    #[case("(, finely chopped)", (None, None, Some("finely chopped")))]
+    #[case(r"(2, cut \(small\))", (Some("2"), None, Some(r"cut \(small\)")))]
+    #[case("(2)", (Some("2"), None, None))]
+    fn test_parse_ingredient_amount_with_note_ok(
+        #[case] input: String,
+        #[case] expected: (Option<&str>, Option<&str>, Option<&str>),
+    ) {
+        let mut input = LocatingSlice::new(input.as_str());
+        let content = parse_ingredient_amount_with_note(&mut input).expect("to work");
+        assert_eq!(expected, (content.quantity, content.unit, content.note));
+        assert!(content.alternatives.is_empty());
+    }
+
+    #[rstest]
+    #[case("(400 gr | 1 can)", (Some("400"), Some("gr")), vec![(Some("1"), Some("can"))], None)]
+    #[case("(400 gr|1 can|2 cups)", (Some("400"), Some("gr")), vec![(Some("1"), Some("can")), (Some("2"), Some("cups"))], None)]
+    #[case("(2 | 300 gr, diced)", (Some("2"), None), vec![(Some("300"), Some("gr"))], Some("diced"))]
+    fn test_parse_ingredient_alternative_amounts_ok(
+        #[case] input: String,
+        #[case] expected_amount: (Option<&str>, Option<&str>),
+        #[case] expected_alternatives: Vec<(Option<&str>, Option<&str>)>,
+        #[case] expected_note: Option<&str>,
+    ) {
+        let mut input = LocatingSlice::new(input.as_str());
+        let content = parse_ingredient_amount_with_note(&mut input).expect("to work");
+        assert_eq!(expected_amount, (content.quantity, content.unit));
+        let alternatives: Vec<_> = content
+            .alternatives
+            .into_iter()
+            .map(|amount| (amount.quantity, amount.unit))
+            .collect();
+        assert_eq!(expected_alternatives, alternatives);
+        assert_eq!(expected_note, content.note);
+    }
+
+    #[rstest]
+    #[case("(1, )")]
+    #[case("(1, chopped (small))")]
+    #[case("(400 gr | )")]
+    fn test_parse_ingredient_amount_with_note_invalid(#[case] input: String) {
+        let mut input = LocatingSlice::new(input.as_str());
+        let res = parse_ingredient_amount_with_note(&mut input);
+        assert!(matches!(res, Err(winnow::error::ErrMode::Cut(_))));
     }
 
     #[rstest]
@@ -571,7 +1355,9 @@ mod test {
     #[case("&{bricks}", "bricks")]
     fn test_parse_material_ok(#[case] input: String, #[case] expected: &str) {
         let mut input = LocatingSlice::new(input.as_str());
-        let material = parse_material(&mut input).expect("Failed to parse material");
+        let material = parse_material(Syntax::default())
+            .parse_next(&mut input)
+            .expect("Failed to parse material");
         assert_eq!(material, expected)
     }
 
@@ -579,10 +1365,68 @@ mod test {
     #[case("t{1 minute}", "1 minute")]
     fn test_parse_timer_ok(#[case] input: String, #[case] expected: &str) {
         let mut input = LocatingSlice::new(input.as_str());
-        let timer = parse_timer(&mut input).expect("Failed to parse timer");
+        let timer = parse_timer(Syntax::default())
+            .parse_next(&mut input)
+            .expect("Failed to parse timer");
         assert_eq!(timer, expected)
     }
 
+    #[rstest]
+    #[case("°{180 C}", ("180", TemperatureScale::Celsius))]
+    #[case("°{ 350 °F }", ("350", TemperatureScale::Fahrenheit))]
+    #[case("°{-18 celsius}", ("-18", TemperatureScale::Celsius))]
+    #[case("°{453.15 K}", ("453.15", TemperatureScale::Kelvin))]
+    #[case("°{gas mark 4}", ("4", TemperatureScale::GasMark))]
+    #[case("°{Gas Mark 1/2}", ("1/2", TemperatureScale::GasMark))]
+    #[case("°{6 gas mark}", ("6", TemperatureScale::GasMark))]
+    fn test_parse_temperature_ok(#[case] input: &str, #[case] expected: (&str, TemperatureScale)) {
+        let mut input = LocatingSlice::new(input);
+        let temperature = parse_temperature(&mut input).expect("Failed to parse temperature");
+        assert_eq!(temperature, expected)
+    }
+
+    #[rstest]
+    #[case("°{180}")]
+    #[case("°{hot}")]
+    #[case("°{180 degrees}")]
+    #[case("°{gas mark}")]
+    fn test_parse_temperature_invalid(#[case] input: &str) {
+        let mut input = LocatingSlice::new(input);
+        let result = parse_temperature(&mut input);
+        assert!(matches!(result, Err(ErrMode::Backtrack(_))), "{result:?}");
+    }
+
+    #[test]
+    fn test_parse_temperature_unclosed() {
+        let mut input = LocatingSlice::new("°{180 C");
+        let result = parse_temperature(&mut input);
+        assert!(matches!(result, Err(ErrMode::Cut(_))), "{result:?}");
+    }
+
+    #[test]
+    fn test_not_a_temperature_is_prose() {
+        let tokens = parse("Bake at °{180} or so").expect("parse failed");
+        assert_eq!(tokens[4], Token::Word("°"));
+        assert!(matches!(tokens[5], Token::Ingredient { name: "180", .. }));
+        let tokens = parse("°{foo}").expect("parse failed");
+        assert_eq!(tokens[0], Token::Word("°"));
+        assert!(matches!(tokens[1], Token::Ingredient { name: "foo", .. }));
+    }
+
+    #[test]
+    fn test_temperature_in_a_step() {
+        let tokens = parse("Preheat to°{180 C}, or °C").expect("parse failed");
+        assert_eq!(tokens[2], Token::Word("to"));
+        assert_eq!(
+            tokens[3],
+            Token::Temperature {
+                value: "180",
+                scale: TemperatureScale::Celsius
+            }
+        );
+        assert_eq!(tokens.last(), Some(&Token::Word("°C")));
+    }
+
     #[rstest]
     #[case("@{woile/tomato-sauce}(200gr)", "woile/tomato-sauce", Some((Some("200"),Some("gr"))))]
     #[case("@{woile/tomato-sauce}", "woile/tomato-sauce", None)]
@@ -593,7 +1437,9 @@ mod test {
         #[case] expected_amount: Option<(Option<&str>, Option<&str>)>,
     ) {
         let mut input = LocatingSlice::new(input.as_str());
-        let (recipe, amount) = parse_recipe_ref(&mut input).unwrap();
+        let (recipe, amount) = parse_recipe_ref(Syntax::default())
+            .parse_next(&mut input)
+            .unwrap();
         assert_eq!(expected_recipe, recipe);
         assert_eq!(expected_amount, amount);
     }
@@ -606,6 +1452,10 @@ mod test {
     #[case(">>    key:     pepe\n", ("key", "pepe"))]
     #[case(">>    key:\t\tpepe\n", ("key", "pepe"))]
     #[case(">>    key:pepe\n", ("key", "pepe"))]
+    #[case(">> key: at the end", ("key", "at the end"))]
+    #[case(">> key:", ("key", ""))]
+    #[case(">> description: long\n  text\n\tin lines\nnot here", ("description", "long\n  text\n\tin lines"))]
+    #[case(">> description: long\n   \n  not here", ("description", "long"))]
     fn test_parse_metadata_ok(#[case] input: String, #[case] expected: (&str, &str)) {
         let mut input = LocatingSlice::new(input.as_str());
         let metadata = parse_metadata(&mut input).expect("Failed to parse metadata");
@@ -634,10 +1484,15 @@ mod test {
 
     #[rstest]
     #[case(" ", Token::Space(" "))]
-    #[case("{holis}(100 gr)", Token::Ingredient { name: "holis", quantity: Some("100"), unit: Some("gr") })]
+    #[case("{holis}(100 gr)", Token::Ingredient { name: "holis", quantity: Some("100"), unit: Some("gr"), alternatives: vec![], note: None, optional: false })]
+    #[case("{onion}(1, finely chopped)", Token::Ingredient { name: "onion", quantity: Some("1"), unit: None, alternatives: vec![], note: Some("finely chopped"), optional: false })]
+    #[case("{chickpeas}(400 gr | 1 can)", Token::Ingredient { name: "chickpeas", quantity: Some("400"), unit: Some("gr"), alternatives: vec![Amount { quantity: Some("1"), unit: Some("can") }], note: None, optional: false })]
+    #[case("{salt}?(1 pinch)", Token::Ingredient { name: "salt", quantity: Some("1"), unit: Some("pinch"), alternatives: vec![], note: None, optional: true })]
     fn test_recipe_value_ok(#[case] input: &str, #[case] expected: Token) {
         let mut input = LocatingSlice::new(input);
-        let token = recipe_value(&mut input).expect("failed to parse token");
+        let token = recipe_value(Syntax::default())
+            .parse_next(&mut input)
+            .expect("failed to parse token");
         assert_eq!(token, expected)
     }
 
@@ -645,7 +1500,7 @@ mod test {
     fn test_recipe_ok() {
         let input = "Boil the quinoa for t{5 minutes} in a &{pot}.\nPut the boiled {quinoa}(200gr) in the base of the bowl.";
         let expected = "Boil the quinoa for 5 minutes in a pot.\nPut the boiled quinoa in the base of the bowl.";
-        let recipe = recipe
+        let recipe = recipe(Syntax::default())
             .parse(LocatingSlice::new(input))
             .expect("parse failed");
         let fmt_recipe = recipe
@@ -657,16 +1512,40 @@ mod test {
         println!("{:?}", recipe);
     }
 
+    #[test]
+    fn test_spans_cover_the_input() {
+        let input =
+            "---\nname: pie\n---\n>> tags: sweet\nBake {apples}(3) for t{40 minutes}\n---\nstory";
+        let tokens = parse_with_spans(input).expect("parse failed");
+        let mut end = 0;
+        for (token, span) in &tokens {
+            assert_eq!(span.start, end, "{token:?}");
+            end = span.end;
+        }
+        assert_eq!(end, input.len());
+        let spans: Vec<&str> = tokens
+            .iter()
+            .map(|(_, span)| &input[span.clone()])
+            .collect();
+        assert!(spans.contains(&"{apples}(3)"));
+        assert!(spans.contains(&"t{40 minutes}"));
+        assert!(spans.contains(&">> tags: sweet"));
+    }
+
     #[rstest]
     #[case(" ", vec![Token::Space(" ")])]
     #[case("\n\nhello", vec![Token::Space("\n\n"), Token::Word("hello")])]
     #[case("hello\n", vec![Token::Word("hello"), Token::Space("\n")])]
     #[case(">> tags: hello\n\nhello", vec![Token::Metadata {key: "tags", value: "hello"}, Token::Space("\n\n"), Token::Word("hello")])]
     #[case(">> source: https://hello.com\n>> tags: hello\n", vec![Token::Metadata {key: "source", value: "https://hello.com"}, Token::Space("\n"), Token::Metadata {key: "tags", value: "hello"}, Token::Space("\n")])]
-    #[case("{holis}(100 gr)", vec![Token::Ingredient { name: "holis", quantity: Some("100"), unit: Some("gr") }])]
+    #[case(">> tags: hello", vec![Token::Metadata {key: "tags", value: "hello"}])]
+    #[case(">> tags: a,\n  b\nhello", vec![Token::Metadata {key: "tags", value: "a,\n  b"}, Token::Space("\n"), Token::Word("hello")])]
+    #[case("{holis}(100 gr)", vec![Token::Ingredient { name: "holis", quantity: Some("100"), unit: Some("gr"), alternatives: vec![], note: None, optional: false }])]
     fn test_recipe_cases_ok(#[case] input: &str, #[case] expected: Vec<Token>) {
         let mut input = LocatingSlice::new(input);
-        let token = recipe(&mut input).expect("failed to parse token");
+        let token = recipe(Syntax::default())
+            .parse_next(&mut input)
+            .expect("failed to parse token");
         assert_eq!(token, expected)
     }
 
@@ -709,6 +1588,9 @@ mod test {
             name: "ingredient",
             quantity: None,
             unit: None,
+            alternatives: vec![],
+            note: None,
+            optional: false,
         },
     ])]
     #[case("l'&{material}", vec![
@@ -728,6 +1610,9 @@ mod test {
             name: "ingredient",
             quantity: None,
             unit: None,
+            alternatives: vec![],
+            note: None,
+            optional: false,
         },
     ])]
     #[case("l`&{material}", vec![
@@ -750,12 +1635,45 @@ mod test {
             name: "白飯",
             quantity: Some("100"),
             unit: Some("g"),
+            alternatives: vec![],
+            note: None,
+            optional: false,
         },
     ])]
     #[case("煮t{mytimer}", vec![
         Token::Word("煮"),
         Token::Timer("mytimer"),
     ])]
+    #[case(r"foot\{timer}", vec![
+        Token::Word(r"foot\{timer}"),
+    ])]
+    #[case(r"\{not an ingredient\}", vec![
+        Token::Word(r"\{not"),
+        Token::Space(" "),
+        Token::Word("an"),
+        Token::Space(" "),
+        Token::Word(r"ingredient\}"),
+    ])]
+    #[case(r"\>> not: metadata", vec![
+        Token::Word(r"\>>"),
+        Token::Space(" "),
+        Token::Word("not:"),
+        Token::Space(" "),
+        Token::Word("metadata"),
+    ])]
+    #[case(r"{ingredient}\(not amount)", vec![
+        Token::Ingredient {
+            name: "ingredient",
+            quantity: None,
+            unit: None,
+            alternatives: vec![],
+            note: None,
+            optional: false,
+        },
+        Token::Word(r"\(not"),
+        Token::Space(" "),
+        Token::Word("amount)"),
+    ])]
     fn test_parse_chunks_without_spaces(#[case] input: &str, #[case] expected: Vec<Token>) {
         let recipe = parse(input).expect("parse failed");
 
@@ -764,6 +1682,122 @@ mod test {
         assert_eq!(expected, recipe);
     }
 
+    #[rstest]
+    #[case("== Filling ==", "Filling")]
+    #[case("==Filling==\nMix", "Filling")]
+    #[case("== Step 1 = mix ==  \n", "Step 1 = mix")]
+    #[case(r"== A \== B ==", r"A \== B")]
+    fn test_parse_section_ok(#[case] input: &str, #[case] expected: &str) {
+        let mut input = LocatingSlice::new(input);
+        let section = parse_section(&mut input).expect("failed to parse section");
+        assert_eq!(section, expected)
+    }
+
+    #[rstest]
+    #[case("== ==")]
+    #[case("== Filling")]
+    #[case("== Filling == and more")]
+    #[case("== Filling\n==")]
+    fn test_parse_section_fail(#[case] input: &str) {
+        let mut input = LocatingSlice::new(input);
+        assert!(parse_section(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let recipe =
+            parse("== Sponge ==\nMix {flour}\n\n== Filling ==\nWhip").expect("parse failed");
+        let sections: Vec<_> = recipe
+            .iter()
+            .filter_map(|token| match token {
+                Token::Section(name) => Some(*name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sections, vec!["Sponge", "Filling"]);
+        assert_eq!(recipe[1], Token::Space("\n"));
+    }
+
+    #[rstest]
+    #[case("Check that x == y ==")]
+    #[case("Mix  == Filling ==")]
+    #[case("{flour}== Filling ==")]
+    fn test_section_only_at_line_start(#[case] input: &str) {
+        let recipe = parse(input).expect("parse failed");
+        assert!(
+            !recipe
+                .iter()
+                .any(|token| matches!(token, Token::Section(_))),
+            "{recipe:?}"
+        );
+    }
+
+    #[rstest]
+    #[case("---\nname: hummus\ntags: [vegan]\n---\n", (FrontMatterFormat::Yaml, "name: hummus\ntags: [vegan]"))]
+    #[case("---\r\nname: hummus\r\n---", (FrontMatterFormat::Yaml, "name: hummus"))]
+    #[case("+++\nname = \"hummus\"\n+++\n", (FrontMatterFormat::Toml, "name = \"hummus\""))]
+    #[case("---\n---\n", (FrontMatterFormat::Yaml, ""))]
+    #[case("---\n\n# recipe\nname:\n  hummus\n---\n", (FrontMatterFormat::Yaml, "\n# recipe\nname:\n  hummus"))]
+    #[case("+++\n[nutrition]\nfat = 1\n+++\n", (FrontMatterFormat::Toml, "[nutrition]\nfat = 1"))]
+    #[case("---\ntext: with --- inside\n----\n---", (FrontMatterFormat::Yaml, "text: with --- inside\n----"))]
+    fn test_parse_front_matter_ok(
+        #[case] input: &str,
+        #[case] expected: (FrontMatterFormat, &str),
+    ) {
+        let mut input = LocatingSlice::new(input);
+        let front_matter = parse_front_matter(&mut input).expect("failed to parse front matter");
+        assert_eq!(front_matter, expected)
+    }
+
+    #[rstest]
+    #[case("---\nname: hummus\n")]
+    #[case("---\nname: hummus\n+++\n")]
+    #[case("--- name: hummus\n---\n")]
+    #[case("---\nJust some prose\n---\n")]
+    #[case("---\n# a comment\n- hummus\n---\n")]
+    #[case("---\nhttp://example.com\n---\n")]
+    #[case("+++\nname: hummus\n+++\n")]
+    fn test_parse_front_matter_fail(#[case] input: &str) {
+        let mut input = LocatingSlice::new(input);
+        assert!(parse_front_matter(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_parse_front_matter_and_backstory() {
+        let input = "---\nname: hummus\n---\nBlend {chickpeas}\n---\nA backstory";
+        let expected = vec![
+            Token::FrontMatter {
+                format: FrontMatterFormat::Yaml,
+                content: "name: hummus",
+            },
+            Token::Word("Blend"),
+            Token::Space(" "),
+            Token::Ingredient {
+                name: "chickpeas",
+                quantity: None,
+                unit: None,
+                alternatives: vec![],
+                note: None,
+                optional: false,
+            },
+            Token::Backstory("A backstory"),
+        ];
+        let recipe = parse(input).expect("parse failed");
+        assert_eq!(expected, recipe);
+    }
+
+    #[test]
+    fn test_front_matter_only_at_the_beginning() {
+        let recipe = parse("Blend\n---\nname: hummus\n---\n").expect("parse failed");
+        assert_eq!(
+            vec![
+                Token::Word("Blend"),
+                Token::Backstory("name: hummus\n---\n")
+            ],
+            recipe
+        );
+    }
+
     #[test]
     fn test_parse_with_backstory_ok() {
         let input = "Foo. \n---\nA backstory";
@@ -787,6 +1821,9 @@ mod test {
             name: "quinoa",
             quantity: Some("200"),
             unit: Some("gr"),
+            alternatives: vec![],
+            note: None,
+            optional: false,
         };
 
         let serialized = serde_json::to_string(&token).expect("failed to serialize");
@@ -800,12 +1837,43 @@ mod test {
             name: "quinoa",
             quantity: Some("200"),
             unit: Some("gr"),
+            alternatives: vec![],
+            note: None,
+            optional: false,
         };
 
         let serialized = serde_json::to_string(&token).expect("failed to serialize");
         assert_eq!(
             serialized,
-            r#"{"token":"Ingredient","content":{"name":"quinoa","quantity":"200","unit":"gr"}}"#
+            r#"{"token":"Ingredient","content":{"name":"quinoa","quantity":"200","unit":"gr","alternatives":[],"note":null,"optional":false}}"#
+        );
+    }
+
+    #[test]
+    fn test_display_unescapes() {
+        let recipe = parse(r"Use {salt \& pepper\}} and not t\{").expect("parse failed");
+        let fmt_recipe = recipe
+            .iter()
+            .fold(String::new(), |acc, val| format!("{acc}{val}"));
+        assert_eq!("Use salt & pepper} and not t{", fmt_recipe);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_token_serialization_unescapes_values() {
+        let token = Token::Ingredient {
+            name: r"curly \} brace",
+            quantity: None,
+            unit: None,
+            alternatives: vec![],
+            note: None,
+            optional: false,
+        };
+
+        let serialized = serde_json::to_string(&token).expect("failed to serialize");
+        assert_eq!(
+            serialized,
+            r#"{"token":"Ingredient","content":{"name":"curly } brace","quantity":null,"unit":null,"alternatives":[],"note":null,"optional":false}}"#
         );
     }
 
@@ -825,4 +1893,97 @@ mod test {
         let schema = schema_for!(Token);
         println!("{}", serde_json::to_string_pretty(&schema).unwrap());
     }
+
+    /// The word parser before scanning bytes, it tries every delimiter at each character
+    fn reference_word<'a>(input: &mut Input<'a>) -> ModalResult<&'a str> {
+        repeat(
+            1..,
+            alt((
+                parse_escaped,
+                (
+                    not(alt((
+                        " ", "\t", "\r", "\n", "'", "`", "{", "t{", "&{", "@{", "°{",
+                    ))),
+                    any,
+                )
+                    .take(),
+            )),
+        )
+        .map(|()| ())
+        .take()
+        .parse_next(input)
+    }
+
+    /// The token parser before dispatching on the first character
+    fn reference_value<'a>(input: &mut Input<'a>) -> ModalResult<Token<'a>> {
+        alt((
+            parse_metadata.map(|(key, value)| Token::Metadata { key, value }),
+            parse_section.map(Token::Section),
+            parse_material(Syntax::default()).map(Token::Material),
+            parse_timer(Syntax::default()).map(Token::Timer),
+            parse_ingredient_token(Syntax::default()),
+            parse_recipe_ref_token(Syntax::default()),
+            parse_temperature.map(|(value, scale)| Token::Temperature { value, scale }),
+            parse_backstory.map(Token::Backstory),
+            parse_comment.map(Token::Comment),
+            parse_special_symbols.map(Token::Word),
+            reference_word.map(Token::Word),
+            "°".map(Token::Word),
+            space1.map(Token::Space),
+            multispace1.map(Token::Space),
+        ))
+        .parse_next(input)
+    }
+
+    fn reference_parse(
+        input: &str,
+    ) -> Result<Vec<SpannedToken<'_>>, ParseError<LocatingSlice<&str>, ContextError>> {
+        (
+            opt(front_matter.with_span()),
+            repeat(0.., reference_value.with_span()),
+        )
+            .map(|(front_matter, tokens): (_, Vec<_>)| {
+                front_matter.into_iter().chain(tokens).collect()
+            })
+            .parse(LocatingSlice::new(input))
+    }
+
+    fn assert_same_tokens(input: &str) {
+        match (parse_with_spans(input), reference_parse(input)) {
+            (Ok(tokens), Ok(expected)) => assert_eq!(tokens, expected, "{input:?}"),
+            (Err(error), Err(expected)) => {
+                assert_eq!(error.offset(), expected.offset(), "{input:?}")
+            }
+            (tokens, expected) => panic!("{input:?}: {tokens:?} != {expected:?}"),
+        }
+    }
+
+    #[test]
+    fn test_same_tokens_as_reference_parser() {
+        let examples = [
+            include_str!("../../../examples/buddha-bowl.recp"),
+            include_str!("../../../examples/hummus.recp"),
+            include_str!("../../../examples/potatoes-jean-claud.recp"),
+            include_str!("../../../examples/spaghetti-a-la-veganesa.recp"),
+        ];
+        for example in examples {
+            assert_same_tokens(example);
+        }
+
+        let recipe = "+++\nname = 'soup'\n+++\n>> tags: soup\n  and more\n== Base ==\n\
+            Fry {onion}?(1 | 2 small, diced) in &{pot} t{5 min} @{stock}(1 l) °{180 C}.\n\
+            l'{ail} `x` (a) /* no */ t\\{ C:\\path \\\\ ñandú 🍲\r\n\n---\nstory";
+        let fragments = [
+            "{", "}", "(", ")", "t{", "&{", "@{", "\\", "\\{", "'", "`", ">>", ">> a: b", "==",
+            "/*", "*/", "\n", "\r\n", " ", "\t", "---", "\n---\n", "ñ", "t", "&", "@", "°", "°{",
+        ];
+        let boundaries = (0..=recipe.len()).filter(|i| recipe.is_char_boundary(*i));
+        for position in boundaries {
+            for fragment in fragments {
+                let mut input = recipe.to_string();
+                input.insert_str(position, fragment);
+                assert_same_tokens(&input);
+            }
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Property tests over recipes generated from the grammar, and over arbitrary text
+
+use proptest::prelude::*;
+
+use crate::{SpannedToken, parse, parse_with_spans, serialize};
+
+fn name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-zA-Z][a-zA-Z ]{0,10}[a-z]",
+        "[a-z]{1,6}",
+        "[a-zñéç ]{0,4}[a-zñéç]",
+        Just(r"salt \& pepper".to_string()),
+        Just(r"crème fraîche \(full fat\)".to_string()),
+        Just(r"curly \} brace".to_string()),
+    ]
+}
+
+/// A quantity, and maybe its unit
+fn amount() -> impl Strategy<Value = String> {
+    let quantity = prop_oneof!["[1-9][0-9]{0,2}", "[1-9]\\.[0-9]{1,2}", "[1-9]/[1-9]", "½"];
+    let unit = prop_oneof!["[a-z]{1,5}", Just("fl oz".to_string())];
+    (quantity, proptest::option::of(unit)).prop_map(|(quantity, unit)| match unit {
+        Some(unit) => format!("{quantity} {unit}"),
+        None => quantity,
+    })
+}
+
+fn note() -> impl Strategy<Value = String> {
+    "[a-z][a-z ]{0,12}[a-z]"
+}
+
+fn ingredient() -> impl Strategy<Value = String> {
+    (
+        name(),
+        any::<bool>(),
+        prop_oneof![
+            Just(String::new()),
+            (
+                proptest::collection::vec(amount(), 1..4),
+                proptest::option::of(note()),
+            )
+                .prop_map(|(amounts, note)| {
+                    let note = note.map(|note| format!(", {note}")).unwrap_or_default();
+                    format!("({}{note})", amounts.join(" | "))
+                }),
+            note().prop_map(|note| format!("(, {note})")),
+        ],
+    )
+        .prop_map(|(name, optional, amount)| {
+            let optional = if optional { "?" } else { "" };
+            format!("{{{name}}}{optional}{amount}")
+        })
+}
+
+fn word() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-zA-Z0-9]{1,8}[.,;:!?]?",
+        "[àéîõüñçß]{1,4}",
+        "[½¾٣]{1,2}",
+        Just("🍲".to_string()),
+        Just(r"t\{".to_string()),
+    ]
+}
+
+/// Something that goes in a step, separated from the next by a space
+fn inline() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => word(),
+        2 => ingredient(),
+        1 => name().prop_map(|name| format!("t{{{name}}}")),
+        1 => name().prop_map(|name| format!("&{{{name}}}")),
+        1 => prop_oneof![
+            ("-?[1-9][0-9]{0,2}", prop_oneof!["C", "°F", "K", "celsius"])
+                .prop_map(|(value, scale)| format!("°{{{value} {scale}}}")),
+            "[1-9]".prop_map(|mark| format!("°{{gas mark {mark}}}")),
+        ],
+        1 => (name(), proptest::option::of(amount()))
+            .prop_map(|(name, amount)| match amount {
+                Some(amount) => format!("@{{{name}}}({amount})"),
+                None => format!("@{{{name}}}"),
+            }),
+        1 => "[a-z ]{0,10}".prop_map(|text| format!("/* {text} */")),
+        1 => word().prop_map(|word| format!("l'{word}")),
+    ]
+}
+
+/// A line of the recipe: metadata, a section or a step
+fn line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        1 => ("[a-z]{1,8}", "[a-z0-9 ,]{0,12}")
+            .prop_map(|(key, value)| format!(">> {key}: {value}")),
+        1 => name().prop_map(|name| format!("== {name} ==")),
+        6 => proptest::collection::vec(inline(), 1..8).prop_map(|parts| parts.join(" ")),
+    ]
+}
+
+/// Recipes that are valid in the grammar
+fn recipe() -> impl Strategy<Value = String> {
+    (
+        proptest::option::of(prop_oneof![
+            "[a-z]{1,6}: [a-z]{1,6}".prop_map(|yaml| format!("---\n{yaml}\n---\n")),
+            "[a-z]{1,6} = [0-9]{1,3}".prop_map(|toml| format!("+++\n{toml}\n+++\n")),
+        ]),
+        proptest::collection::vec(line(), 0..10),
+        proptest::option::of("[a-zA-Z .]{0,30}"),
+    )
+        .prop_map(|(front_matter, lines, backstory)| {
+            let mut recipe = front_matter.unwrap_or_default();
+            recipe.push_str(&lines.join("\n"));
+            if let Some(backstory) = backstory {
+                recipe.push_str("\n---\n");
+                recipe.push_str(&backstory);
+            }
+            recipe
+        })
+}
+
+/// The spans follow each other, cover the input and fall on character boundaries
+fn assert_spans_in_bounds(input: &str, tokens: &[SpannedToken<'_>]) {
+    let mut end = 0;
+    for (_, span) in tokens {
+        assert_eq!(span.start, end, "gap before {span:?} in {input:?}");
+        assert!(span.start < span.end && span.end <= input.len());
+        assert!(input.is_char_boundary(span.start) && input.is_char_boundary(span.end));
+        end = span.end;
+    }
+    assert_eq!(end, input.len(), "the tokens don't cover {input:?}");
+}
+
+fn assert_roundtrip(input: &str) {
+    let tokens = parse(input).unwrap();
+    let serialized = serialize(&tokens);
+    let reparsed = parse(&serialized)
+        .unwrap_or_else(|error| panic!("{serialized:?} could not be parsed: {error}"));
+    assert_eq!(
+        tokens, reparsed,
+        "{input:?} was serialized as {serialized:?}"
+    );
+}
+
+proptest! {
+    #[test]
+    fn generated_recipes_are_parsed(input in recipe()) {
+        let tokens = parse_with_spans(&input);
+        prop_assert!(tokens.is_ok(), "{input:?}: {tokens:?}");
+        assert_spans_in_bounds(&input, &tokens.unwrap());
+    }
+
+    #[test]
+    fn generated_recipes_roundtrip(input in recipe()) {
+        assert_roundtrip(&input);
+    }
+
+    #[test]
+    fn any_text_is_parsed_without_panics(input in "\\PC*") {
+        if let Ok(tokens) = parse_with_spans(&input) {
+            assert_spans_in_bounds(&input, &tokens);
+            assert_roundtrip(&input);
+        }
+    }
+
+    #[test]
+    fn recipe_symbols_are_parsed_without_panics(
+        input in "([{}()t&@°?|,:>=/*\\\\' \n\r\t-]|[a-z0-9]|[½٣é🍲]|gas mark){0,40}"
+    ) {
+        if let Ok(tokens) = parse_with_spans(&input) {
+            assert_spans_in_bounds(&input, &tokens);
+            assert_roundtrip(&input);
+        }
+    }
+}
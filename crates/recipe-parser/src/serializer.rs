@@ -0,0 +1,192 @@
+use std::fmt::Write;
+
+use crate::{Amount, FrontMatterFormat, TemperatureScale, Token};
+
+fn join_amount(amount: &Amount<'_>) -> String {
+    [amount.quantity, amount.unit]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn write_amount(out: &mut String, amounts: &[Amount<'_>], note: Option<&str>) {
+    let amounts: Vec<String> = amounts
+        .iter()
+        .filter(|amount| amount.quantity.is_some() || amount.unit.is_some())
+        .map(join_amount)
+        .collect();
+    if amounts.is_empty() && note.is_none() {
+        return;
+    }
+    let note = note
+        .map(|note| {
+            let space = if needs_space(note) { " " } else { "" };
+            format!(", {note}{space}")
+        })
+        .unwrap_or_default();
+    write!(out, "({}{})", amounts.join(" | "), note).expect("writing to a String never fails");
+}
+
+/// Names and notes are trimmed when parsed, so a space can be written after them.
+///
+/// It's needed when the value is empty, because `{}` is not valid, or ends with a
+/// backslash, which would escape the closing brace.
+fn needs_space(value: &str) -> bool {
+    value.is_empty() || value.ends_with('\\')
+}
+
+fn write_curly(out: &mut String, prefix: &str, name: &str) {
+    out.push_str(prefix);
+    out.push('{');
+    out.push_str(name);
+    if needs_space(name) {
+        out.push(' ');
+    }
+    out.push('}');
+}
+
+/// Write a single token back into recipe-lang.
+///
+/// Token values are raw, which means escaped characters are written as they are.
+pub(crate) fn write_token(out: &mut String, token: &Token<'_>) {
+    match token {
+        Token::Metadata { key, value } => {
+            out.push_str(">> ");
+            out.push_str(key);
+            out.push_str(": ");
+            out.push_str(value);
+        }
+        Token::Ingredient {
+            name,
+            quantity,
+            unit,
+            alternatives,
+            note,
+            optional,
+        } => {
+            write_curly(out, "", name);
+            if *optional {
+                out.push('?');
+            }
+            let primary = Amount {
+                quantity: *quantity,
+                unit: *unit,
+            };
+            let amounts: Vec<Amount> = std::iter::once(primary)
+                .chain(alternatives.iter().cloned())
+                .collect();
+            let len = out.len();
+            write_amount(out, &amounts, *note);
+            // the `?` is only read before an amount
+            if *optional && out.len() == len {
+                out.push_str("()");
+            }
+        }
+        Token::RecipeRef {
+            name,
+            quantity,
+            unit,
+        } => {
+            write_curly(out, "@", name);
+            let amount = Amount {
+                quantity: *quantity,
+                unit: *unit,
+            };
+            write_amount(out, &[amount], None);
+        }
+        Token::Timer(v) => write_curly(out, "t", v),
+        Token::Material(v) => write_curly(out, "&", v),
+        Token::Temperature {
+            value,
+            scale: TemperatureScale::GasMark,
+        } => write!(out, "°{{gas mark {value}}}").expect("writing to a String never fails"),
+        Token::Temperature { value, scale } => {
+            write!(out, "°{{{value} {scale}}}").expect("writing to a String never fails")
+        }
+        Token::Tag {
+            prefix,
+            body,
+            amount,
+        } => {
+            write_curly(out, prefix, body);
+            if let Some(amount) = amount {
+                write_amount(out, std::slice::from_ref(amount), None);
+            }
+        }
+        Token::Word(v) | Token::Space(v) => out.push_str(v),
+        Token::Comment(v) => {
+            out.push_str("/* ");
+            out.push_str(v);
+            out.push_str(" */");
+        }
+        Token::Section(v) => {
+            out.push_str("== ");
+            out.push_str(v);
+            out.push_str(" ==");
+        }
+        Token::Backstory(v) => {
+            out.push_str("\n---\n");
+            out.push_str(v);
+        }
+        Token::FrontMatter { format, content } => {
+            let fence = match format {
+                FrontMatterFormat::Yaml => "---",
+                FrontMatterFormat::Toml => "+++",
+            };
+            out.push_str(fence);
+            out.push('\n');
+            if !content.is_empty() {
+                out.push_str(content);
+                out.push('\n');
+            }
+            out.push_str(fence);
+            out.push('\n');
+        }
+    }
+}
+
+/// Serialize tokens back into a recipe.
+///
+/// Parsing the serialized recipe produces the same tokens.
+///
+/// ```
+/// use recipe_parser::{parse, serialize};
+///
+/// let input = "Add {crème fraîche \\(full fat\\)}(200 ml) and stir for t{5 minutes}";
+/// let tokens = parse(input).expect("recipe could not be parsed");
+/// assert_eq!(serialize(&tokens), input);
+/// ```
+pub fn serialize(tokens: &[Token<'_>]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        write_token(&mut out, token);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+    use rstest::*;
+
+    #[rstest]
+    #[case(">> name: salad\nTake {tomatoes}(2) and a &{bowl}.")]
+    #[case("Boil for t{10 minutes} with @{woile/sauce}(100 ml) /* later */ \n---\nstory")]
+    #[case(r"Write t\{ and \{ literally with {salt \& pepper}")]
+    #[case("Add {onion}(1, finely chopped), {salt}?(1 pinch) and {pepper}?(, to taste)")]
+    #[case("Add {chickpeas}(400 gr | 1 can, drained)")]
+    #[case("Add {chili flakes}?() if you like. Did you add the {salt}?")]
+    #[case("---\nname: hummus\n---\n>> tags: vegan\nBlend")]
+    #[case("+++\n+++\nBlend")]
+    #[case("Add {\t} and t{ }")]
+    #[case(r"Add {\ }0, &{a\ } and {b}(1, c\ )")]
+    #[case("== Sponge ==\nMix {flour}(200 gr)\n== Filling ==\nWhip")]
+    fn test_serialize_roundtrip(#[case] input: &str) {
+        let tokens = parse(input).expect("parse failed");
+        let serialized = serialize(&tokens);
+        let reparsed = parse(&serialized).expect("parse of serialized recipe failed");
+        assert_eq!(tokens, reparsed);
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt::Display;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::escape::is_escapable;
+use crate::{SpannedToken, Token};
+
+/// A version of the recipe-lang spec, like `0.2`.
+///
+/// Patch versions don't change the grammar, so only the major and minor numbers
+/// are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpecVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl SpecVersion {
+    pub const V0_2: SpecVersion = SpecVersion { major: 0, minor: 2 };
+    pub const V0_3: SpecVersion = SpecVersion { major: 0, minor: 3 };
+    pub const V0_4: SpecVersion = SpecVersion { major: 0, minor: 4 };
+
+    /// Versions the parser can check a recipe against
+    pub const SUPPORTED: &[SpecVersion] =
+        &[SpecVersion::V0_2, SpecVersion::V0_3, SpecVersion::V0_4];
+
+    pub fn is_supported(&self) -> bool {
+        SpecVersion::SUPPORTED.contains(self)
+    }
+}
+
+/// The version of the spec implemented by the parser
+pub const SPEC_VERSION: SpecVersion = SpecVersion::V0_4;
+
+impl Display for SpecVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for SpecVersion {
+    type Err = String;
+
+    /// Parse `0.2` or `0.2.0`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid spec version `{value}`, expected one like `0.2`");
+        let mut parts = value.trim().split('.');
+        let mut number = || -> Result<u32, String> {
+            parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(invalid)
+        };
+        let version = SpecVersion {
+            major: number()?,
+            minor: number()?,
+        };
+        match parts.next() {
+            None => Ok(version),
+            Some(patch) if patch.parse::<u32>().is_ok() && parts.next().is_none() => Ok(version),
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+/// Metadata key a recipe uses to declare the version of the spec it's written in
+pub const VERSION_KEY: &str = "recp-version";
+
+/// The version declared with `>> recp-version: 0.2`, if any
+pub fn declared_version<'a>(tokens: &[SpannedToken<'a>]) -> Option<&'a str> {
+    tokens.iter().find_map(|(token, _)| match token {
+        Token::Metadata { key, value } if is_version_key(key) => Some(value.trim()),
+        _ => None,
+    })
+}
+
+/// Keys are case insensitive, and `_` or spaces are the same as `-`
+fn is_version_key(key: &str) -> bool {
+    let key = key.trim().to_lowercase().replace(['_', ' '], "-");
+    key == VERSION_KEY
+}
+
+/// A construct used in a recipe that the target version of the spec doesn't have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    pub construct: String,
+    /// The first version with the construct
+    pub since: SpecVersion,
+    /// Byte range of the token using it
+    pub span: Range<usize>,
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} needs recp-version {}", self.construct, self.since)
+    }
+}
+
+/// A backslash followed by punctuation
+fn has_escape(value: &str) -> bool {
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek().is_some_and(|next| is_escapable(*next)) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Names in 0.2 are letters, numbers, spaces and a few symbols
+fn new_name_character(name: &str) -> Option<char> {
+    name.chars()
+        .find(|c| !c.is_alphanumeric() && !"\t /-_@.,%#'".contains(*c))
+}
+
+/// Constructs of the tokens that `version` doesn't support
+pub fn unsupported(tokens: &[SpannedToken<'_>], version: SpecVersion) -> Vec<Unsupported> {
+    let mut found = vec![];
+    for (token, span) in tokens {
+        let mut report = |construct: String, since: SpecVersion| {
+            if since > version {
+                found.push(Unsupported {
+                    construct,
+                    since,
+                    span: span.clone(),
+                })
+            }
+        };
+        let name = match token {
+            Token::Ingredient {
+                name,
+                alternatives,
+                note,
+                optional,
+                ..
+            } => {
+                if *optional {
+                    report("an optional ingredient".to_string(), SpecVersion::V0_3);
+                }
+                if note.is_some() {
+                    report("a preparation note".to_string(), SpecVersion::V0_3);
+                }
+                if !alternatives.is_empty() {
+                    report("an alternative amount".to_string(), SpecVersion::V0_3);
+                }
+                Some(name)
+            }
+            Token::RecipeRef { name, .. }
+            | Token::Timer(name)
+            | Token::Material(name)
+            | Token::Tag { body: name, .. } => Some(name),
+            Token::Temperature { .. } => {
+                report("a temperature".to_string(), SpecVersion::V0_4);
+                None
+            }
+            Token::Section(_) => {
+                report("a section".to_string(), SpecVersion::V0_3);
+                None
+            }
+            Token::FrontMatter { .. } => {
+                report("a front matter".to_string(), SpecVersion::V0_3);
+                None
+            }
+            Token::Metadata { value, .. } if value.contains('\n') => {
+                report(
+                    "a metadata value in many lines".to_string(),
+                    SpecVersion::V0_3,
+                );
+                None
+            }
+            Token::Word(word) if has_escape(word) => {
+                report("an escaped character".to_string(), SpecVersion::V0_3);
+                None
+            }
+            _ => None,
+        };
+        if let Some(name) = name {
+            if has_escape(name) {
+                report("an escaped character".to_string(), SpecVersion::V0_3);
+            } else if let Some(c) = new_name_character(name) {
+                report(format!("the character `{c}` in a name"), SpecVersion::V0_3);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_with_spans;
+    use rstest::*;
+
+    #[rstest]
+    #[case("0.2", Ok(SpecVersion::V0_2))]
+    #[case(" 0.3.1 ", Ok(SpecVersion::V0_3))]
+    #[case("1.0", Ok(SpecVersion { major: 1, minor: 0 }))]
+    #[case("1", Err(()))]
+    #[case("0.2.x", Err(()))]
+    #[case("latest", Err(()))]
+    fn test_parse_spec_version(#[case] input: &str, #[case] expected: Result<SpecVersion, ()>) {
+        assert_eq!(input.parse::<SpecVersion>().map_err(|_| ()), expected);
+    }
+
+    #[rstest]
+    #[case(">> name: soup\nBoil {water}(1 l) in a &{pot} for t{5 min}", vec![])]
+    #[case("== Base ==\nBoil", vec!["a section needs recp-version 0.3"])]
+    #[case(
+        "{salt}?(1 pinch | 2 g, ground)",
+        vec![
+            "an optional ingredient needs recp-version 0.3",
+            "a preparation note needs recp-version 0.3",
+            "an alternative amount needs recp-version 0.3",
+        ]
+    )]
+    #[case(
+        r"Add {crème fraîche (full)} and t\{",
+        vec![
+            "the character `(` in a name needs recp-version 0.3",
+            "an escaped character needs recp-version 0.3",
+        ]
+    )]
+    #[case(
+        ">> description: a soup\n  for winter",
+        vec!["a metadata value in many lines needs recp-version 0.3"]
+    )]
+    #[case("---\nname: soup\n---\n", vec!["a front matter needs recp-version 0.3"])]
+    fn test_unsupported_in_0_2(#[case] input: &str, #[case] expected: Vec<&str>) {
+        let tokens = parse_with_spans(input).unwrap();
+        let found: Vec<String> = unsupported(&tokens, SpecVersion::V0_2)
+            .iter()
+            .map(Unsupported::to_string)
+            .collect();
+        assert_eq!(found, expected);
+        assert!(unsupported(&tokens, SpecVersion::V0_3).is_empty());
+    }
+
+    #[test]
+    fn test_temperature_needs_0_4() {
+        let tokens = parse_with_spans("Bake at °{180 C}").unwrap();
+        let found: Vec<String> = unsupported(&tokens, SpecVersion::V0_3)
+            .iter()
+            .map(Unsupported::to_string)
+            .collect();
+        assert_eq!(found, vec!["a temperature needs recp-version 0.4"]);
+        assert_eq!(unsupported(&tokens, SpecVersion::V0_2).len(), 1);
+        assert!(unsupported(&tokens, SPEC_VERSION).is_empty());
+    }
+
+    #[test]
+    fn test_declared_version() {
+        let tokens = parse_with_spans(">> Recp_Version: 0.2 \nBoil").unwrap();
+        assert_eq!(declared_version(&tokens), Some("0.2"));
+        let tokens = parse_with_spans(">> name: soup").unwrap();
+        assert_eq!(declared_version(&tokens), None);
+    }
+}
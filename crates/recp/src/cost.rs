@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Recipe;
+use crate::index::resolve_ref;
+use crate::names::Normalizer;
+use crate::recipe::{Amount, Ingredient};
+use crate::units::{Dimension, lookup_unit};
+
+/// The price of an amount of an ingredient, e.g: `chickpeas,1.20,400,g,EUR`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Price {
+    pub name: String,
+    pub price: f64,
+    /// The amount the price is for
+    pub quantity: f64,
+    /// `None` when the price is per piece
+    #[serde(default)]
+    pub unit: Option<String>,
+    pub currency: String,
+}
+
+impl Price {
+    /// Cost of an amount, when it can be converted to the unit of the price
+    fn cost_of(&self, amount: &Amount) -> Option<f64> {
+        let value = amount.value()?;
+        let quantity = match (&self.unit, &amount.unit) {
+            (None, None) => value,
+            (Some(price_unit), Some(unit)) if price_unit.eq_ignore_ascii_case(unit) => value,
+            (Some(price_unit), Some(_)) if lookup_unit(price_unit).is_some() => {
+                amount.convert_to(price_unit)?
+            }
+            _ => return None,
+        };
+        Some(quantity / self.quantity * self.price)
+    }
+}
+
+/// Prices of the ingredients, from a CSV with the headers `name,price,quantity,unit,currency`.
+///
+/// Each price is for the given quantity and unit, and can use its own currency:
+///
+/// ```csv
+/// name,price,quantity,unit,currency
+/// chickpeas,1.20,400,g,EUR
+/// lemon,0.30,1,,USD
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PriceList {
+    pub prices: Vec<Price>,
+}
+
+impl PriceList {
+    pub fn parse_csv(content: &str) -> Result<PriceList, String> {
+        let prices = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<Price>, _>>()
+            .map_err(|err| format!("invalid price list: {err}"))?;
+        if let Some(price) = prices.iter().find(|price| price.quantity <= 0.0) {
+            return Err(format!(
+                "invalid price list: the quantity of {} must be positive",
+                price.name
+            ));
+        }
+        Ok(PriceList { prices })
+    }
+
+    pub fn load(path: &Path) -> Result<PriceList, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        PriceList::parse_csv(&content)
+    }
+
+    /// All the prices of an ingredient, by its normalized name
+    pub fn get(&self, name: &str, normalizer: &Normalizer, lang: Option<&str>) -> Vec<&Price> {
+        let name = normalizer.normalize(name, lang);
+        self.prices
+            .iter()
+            .filter(|price| normalizer.normalize(&price.name, lang) == name)
+            .collect()
+    }
+}
+
+/// The cost of an ingredient of the recipe
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostItem {
+    pub name: String,
+    pub cost: f64,
+    pub currency: String,
+    /// The referenced recipe the ingredient comes from
+    pub recipe: Option<String>,
+}
+
+/// An ingredient without cost, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unpriced {
+    pub name: String,
+    pub reason: String,
+    pub recipe: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostReport {
+    pub items: Vec<CostItem>,
+    pub unpriced: Vec<Unpriced>,
+    /// Total by currency
+    pub totals: BTreeMap<String, f64>,
+    pub servings: Option<u32>,
+    /// Cost of a serving by currency
+    pub per_serving: BTreeMap<String, f64>,
+}
+
+/// Shared while costing a recipe and the recipes it references
+struct Context<'a> {
+    prices: &'a PriceList,
+    normalizer: &'a Normalizer,
+    /// Recipes being costed, to stop at circular references
+    stack: Vec<PathBuf>,
+}
+
+/// How much of a referenced recipe is used.
+///
+/// The amount of the reference is compared with the `yield` of the recipe,
+/// or with its `servings` when the amount has no unit. A reference without amount
+/// uses the whole recipe.
+fn ref_factor(reference: &Ingredient, recipe: &Recipe) -> Option<f64> {
+    let amount = reference.amount();
+    if amount.quantity.is_none() {
+        return Some(1.0);
+    }
+    if let Some(recipe_yield) = recipe.metadata.recipe_yield() {
+        let (used, dimension) = amount.to_base()?;
+        let (produced, yield_dimension) = recipe_yield.to_base()?;
+        return (dimension == yield_dimension && produced > 0.0).then(|| used / produced);
+    }
+    match (amount.dimension(), recipe.metadata.servings()) {
+        (Some(Dimension::Count), Some(servings)) => Some(amount.value()? / servings as f64),
+        _ => None,
+    }
+}
+
+fn collect(
+    recipe: &Recipe,
+    dir: &Path,
+    factor: f64,
+    from: Option<&str>,
+    context: &mut Context,
+    report: &mut CostReport,
+) {
+    let lang = recipe.metadata.lang();
+    let recipe_name = from.map(str::to_string);
+    for ingredient in recipe.ingredients.iter().filter(|i| !i.optional) {
+        let unpriced = |reason: &str| Unpriced {
+            name: ingredient.name.clone(),
+            reason: reason.to_string(),
+            recipe: recipe_name.clone(),
+        };
+        let prices = context
+            .prices
+            .get(&ingredient.name, context.normalizer, lang);
+        if prices.is_empty() {
+            report.unpriced.push(unpriced("not in the price list"));
+            continue;
+        }
+        let cost = ingredient.amounts().find_map(|amount| {
+            prices
+                .iter()
+                .find_map(|price| Some((price.cost_of(&amount)?, &price.currency)))
+        });
+        match cost {
+            Some((cost, currency)) => report.items.push(CostItem {
+                name: ingredient.name.clone(),
+                cost: cost * factor,
+                currency: currency.clone(),
+                recipe: recipe_name.clone(),
+            }),
+            None => report.unpriced.push(unpriced(
+                "amount can't be converted to the unit of the price",
+            )),
+        }
+    }
+    for reference in &recipe.recipes_refs {
+        let unpriced = |reason: &str| Unpriced {
+            name: reference.name.clone(),
+            reason: reason.to_string(),
+            recipe: recipe_name.clone(),
+        };
+        let Some(path) = resolve_ref(dir, &reference.name) else {
+            report
+                .unpriced
+                .push(unpriced("referenced recipe not found"));
+            continue;
+        };
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if context.stack.contains(&canonical) {
+            report.unpriced.push(unpriced("circular reference"));
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            report
+                .unpriced
+                .push(unpriced("referenced recipe can't be read"));
+            continue;
+        };
+        let Ok(referenced) = Recipe::try_from(content.as_str()) else {
+            report
+                .unpriced
+                .push(unpriced("referenced recipe can't be parsed"));
+            continue;
+        };
+        let Some(ref_factor) = ref_factor(reference, &referenced) else {
+            report.unpriced.push(unpriced(
+                "amount can't be compared with the yield or servings of the recipe",
+            ));
+            continue;
+        };
+        context.stack.push(canonical);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        collect(
+            &referenced,
+            dir,
+            factor * ref_factor,
+            Some(&reference.name),
+            context,
+            report,
+        );
+        context.stack.pop();
+    }
+}
+
+/// Estimate the cost of a recipe found at `path`.
+///
+/// Referenced recipes are looked up relative to the recipe, and their ingredients
+/// are scaled to the amount used. Optional ingredients are left out.
+pub fn cost(
+    recipe: &Recipe,
+    path: &Path,
+    prices: &PriceList,
+    normalizer: &Normalizer,
+) -> CostReport {
+    let mut context = Context {
+        prices,
+        normalizer,
+        stack: vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())],
+    };
+    let mut report = CostReport::default();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    collect(recipe, dir, 1.0, None, &mut context, &mut report);
+    for item in &report.items {
+        *report.totals.entry(item.currency.clone()).or_default() += item.cost;
+    }
+    report.servings = recipe.metadata.servings();
+    if let Some(servings) = report.servings {
+        report.per_serving = report
+            .totals
+            .iter()
+            .map(|(currency, total)| (currency.clone(), total / servings as f64))
+            .collect();
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PRICES: &str = "name,price,quantity,unit,currency
+chickpeas,1.20,400,g,EUR
+chickpeas,0.90,1,can,EUR
+lemons,0.30,1,,USD
+olive oil,8,1,l,EUR
+tahini,5,300,g,EUR
+";
+
+    fn total(report: &CostReport, currency: &str) -> f64 {
+        (report.totals[currency] * 100.0).round() / 100.0
+    }
+
+    #[test]
+    fn test_price_list() {
+        let prices = PriceList::parse_csv(PRICES).unwrap();
+        assert_eq!(prices.prices.len(), 5);
+        assert_eq!(prices.prices[2].unit, None);
+        let normalizer = Normalizer::default();
+        assert_eq!(prices.get("Chickpea", &normalizer, None).len(), 2);
+        assert!(PriceList::parse_csv("name,price,quantity,unit,currency\nsalt,1,0,g,EUR").is_err());
+    }
+
+    #[test]
+    fn test_cost() {
+        let prices = PriceList::parse_csv(PRICES).unwrap();
+        let recipe = Recipe::try_from(
+            ">> servings: 2\nMix {chickpeas}(1 can), {lemon}(2), {olive oil}(2 tbsp), {tahini}(2 tsp), {salt} and {mint}?()",
+        )
+        .unwrap();
+        let report = cost(
+            &recipe,
+            Path::new("hummus.recp"),
+            &prices,
+            &Normalizer::default(),
+        );
+        assert_eq!(total(&report, "USD"), 0.6);
+        // a can of chickpeas and 2 tbsp of oil, the tahini in tsp can't be priced in grams
+        assert_eq!(total(&report, "EUR"), 1.14);
+        assert!((report.per_serving["USD"] - 0.3).abs() < 1e-9);
+        let unpriced: Vec<_> = report.unpriced.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(unpriced, vec!["tahini", "salt"]);
+    }
+
+    #[test]
+    fn test_cost_follows_references() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/cost");
+        let prices = PriceList::parse_csv(PRICES).unwrap();
+        let path = dir.join("bowl.recp");
+        let content = fs::read_to_string(&path).unwrap();
+        let recipe = Recipe::try_from(content.as_str()).unwrap();
+        let report = cost(&recipe, &path, &prices, &Normalizer::default());
+        assert_eq!(total(&report, "EUR"), 0.3);
+        assert_eq!(report.items[0].recipe.as_deref(), Some("hummus"));
+        let unpriced: Vec<_> = report
+            .unpriced
+            .iter()
+            .map(|u| (u.name.as_str(), u.reason.as_str()))
+            .collect();
+        assert_eq!(
+            unpriced,
+            vec![
+                ("bowl", "circular reference"),
+                ("missing", "referenced recipe not found")
+            ]
+        );
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::Recipe;
+use crate::index::resolve_ref;
+use crate::names::Normalizer;
+
+/// Attributes reported as allergens
+pub const ALLERGENS: &[&str] = &[
+    "gluten",
+    "crustaceans",
+    "egg",
+    "fish",
+    "peanuts",
+    "soy",
+    "dairy",
+    "nuts",
+    "celery",
+    "mustard",
+    "sesame",
+    "sulphites",
+    "lupin",
+    "molluscs",
+];
+
+/// Diets and the attributes they exclude, used unless the mapping file redefines them
+const DIETS: &[(&str, &[&str])] = &[
+    (
+        "vegan",
+        &[
+            "meat",
+            "fish",
+            "crustaceans",
+            "molluscs",
+            "dairy",
+            "egg",
+            "honey",
+            "animal",
+        ],
+    ),
+    (
+        "vegetarian",
+        &["meat", "fish", "crustaceans", "molluscs", "animal"],
+    ),
+    ("pescatarian", &["meat"]),
+    ("gluten-free", &["gluten"]),
+    ("dairy-free", &["dairy"]),
+    ("nut-free", &["nuts", "peanuts"]),
+    ("egg-free", &["egg"]),
+];
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Maps ingredients to their attributes, like `dairy` or `gluten`, and diets to the
+/// attributes they exclude.
+///
+/// The mapping file has an ingredient and its attributes per line, and diets can be
+/// added or redefined with lines starting with `diet`:
+///
+/// ```text
+/// # ingredient: attributes
+/// butter: dairy
+/// flour: gluten
+/// # diet name: excluded attributes
+/// diet keto: sugar, grain
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attributes {
+    pub ingredients: Vec<(String, Vec<String>)>,
+    pub diets: Vec<(String, Vec<String>)>,
+}
+
+impl Default for Attributes {
+    fn default() -> Self {
+        Attributes {
+            ingredients: vec![],
+            diets: DIETS
+                .iter()
+                .map(|(diet, excluded)| {
+                    (
+                        diet.to_string(),
+                        excluded.iter().map(|a| a.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Attributes {
+    pub fn parse(content: &str) -> Result<Attributes, String> {
+        let mut attributes = Attributes::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, values)) = line.split_once(':') else {
+                return Err(format!(
+                    "line {}: expected `ingredient: attribute, attribute`",
+                    number + 1
+                ));
+            };
+            let values = split_list(values);
+            match name.trim().strip_prefix("diet ") {
+                Some(diet) => {
+                    let diet = diet.trim().to_lowercase();
+                    attributes.diets.retain(|(name, _)| *name != diet);
+                    attributes.diets.push((diet, values));
+                }
+                None => attributes
+                    .ingredients
+                    .push((name.trim().to_string(), values)),
+            }
+        }
+        Ok(attributes)
+    }
+
+    pub fn load(path: &Path) -> Result<Attributes, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        Attributes::parse(&content)
+    }
+
+    /// Attributes of an ingredient, `None` when it's not in the mapping.
+    ///
+    /// The names are normalized, and a mapped name matches the ingredients containing
+    /// its words: `butter` matches `unsalted butter`. The most specific names win, so
+    /// `peanut butter` doesn't get the attributes of `butter` when both are mapped.
+    pub fn get(
+        &self,
+        name: &str,
+        normalizer: &Normalizer,
+        lang: Option<&str>,
+    ) -> Option<Vec<&str>> {
+        let name = format!(" {} ", normalizer.normalize(name, lang));
+        let matches: Vec<(String, &Vec<String>)> = self
+            .ingredients
+            .iter()
+            .map(|(mapped, attributes)| {
+                (
+                    format!(" {} ", normalizer.normalize(mapped, lang)),
+                    attributes,
+                )
+            })
+            .filter(|(mapped, _)| name.contains(mapped.as_str()))
+            .collect();
+        let specific = matches.iter().filter(|(mapped, _)| {
+            !matches
+                .iter()
+                .any(|(other, _)| other.len() > mapped.len() && other.contains(mapped.as_str()))
+        });
+        let mut found: Option<Vec<&str>> = None;
+        for (_, attributes) in specific {
+            found
+                .get_or_insert_with(Vec::new)
+                .extend(attributes.iter().map(String::as_str));
+        }
+        found
+    }
+}
+
+/// An ingredient that doesn't fit a diet declared in the tags
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contradiction {
+    pub diet: String,
+    pub ingredient: String,
+    pub attribute: String,
+    /// The referenced recipe the ingredient comes from
+    pub recipe: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DietReport {
+    /// Allergens of the ingredients, sorted
+    pub allergens: Vec<String>,
+    /// Diets the mapped ingredients fit, in the order they are defined
+    pub diets: Vec<String>,
+    /// Diets in the tags that the ingredients don't fit
+    pub contradictions: Vec<Contradiction>,
+    /// Ingredients not in the mapping, the diets don't take them into account
+    pub unknown: Vec<String>,
+}
+
+impl DietReport {
+    /// Text of a contradiction, like `tagged vegan, but butter is dairy`
+    pub fn describe(contradiction: &Contradiction) -> String {
+        let from = contradiction
+            .recipe
+            .as_ref()
+            .map(|recipe| format!(" (from {recipe})"))
+            .unwrap_or_default();
+        format!(
+            "tagged {}, but {}{} is {}",
+            contradiction.diet, contradiction.ingredient, from, contradiction.attribute
+        )
+    }
+}
+
+/// An attribute of an ingredient, and the referenced recipe it comes from
+struct Found {
+    ingredient: String,
+    attribute: String,
+    recipe: Option<String>,
+}
+
+/// Walks a recipe and the recipes it references
+struct Collector<'a> {
+    attributes: &'a Attributes,
+    normalizer: &'a Normalizer,
+    /// Recipes being walked, to stop at circular references
+    stack: Vec<PathBuf>,
+    found: Vec<Found>,
+    unknown: Vec<String>,
+}
+
+impl Collector<'_> {
+    fn collect(&mut self, recipe: &Recipe, dir: Option<&Path>, from: Option<&str>) {
+        let lang = recipe.metadata.lang();
+        for ingredient in &recipe.ingredients {
+            match self.attributes.get(&ingredient.name, self.normalizer, lang) {
+                Some(values) => self.found.extend(values.into_iter().map(|attribute| Found {
+                    ingredient: ingredient.name.clone(),
+                    attribute: attribute.to_string(),
+                    recipe: from.map(str::to_string),
+                })),
+                None if !self.unknown.contains(&ingredient.name) => {
+                    self.unknown.push(ingredient.name.clone())
+                }
+                None => {}
+            }
+        }
+        let Some(dir) = dir else {
+            return;
+        };
+        for reference in &recipe.recipes_refs {
+            let Some(path) = resolve_ref(dir, &reference.name) else {
+                self.unknown.push(reference.name.clone());
+                continue;
+            };
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if self.stack.contains(&canonical) {
+                continue;
+            }
+            // a recipe that can't be read is unknown, not a recipe without ingredients
+            let Ok(content) = fs::read_to_string(&path) else {
+                self.unknown.push(reference.name.clone());
+                continue;
+            };
+            let Ok(referenced) = Recipe::try_from(content.as_str()) else {
+                self.unknown.push(reference.name.clone());
+                continue;
+            };
+            self.stack.push(canonical);
+            self.collect(&referenced, path.parent(), Some(&reference.name));
+            self.stack.pop();
+        }
+    }
+}
+
+/// Infer the allergens and diets of a recipe, and check the diets of its tags.
+///
+/// When the `path` of the recipe is given, the ingredients of the referenced
+/// recipes are included.
+pub fn analyze(
+    recipe: &Recipe,
+    path: Option<&Path>,
+    attributes: &Attributes,
+    normalizer: &Normalizer,
+) -> DietReport {
+    let mut collector = Collector {
+        attributes,
+        normalizer,
+        stack: path
+            .map(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()))
+            .into_iter()
+            .collect(),
+        found: vec![],
+        unknown: vec![],
+    };
+    collector.collect(
+        recipe,
+        path.map(|path| path.parent().unwrap_or(Path::new(""))),
+        None,
+    );
+    let Collector { found, unknown, .. } = collector;
+    let allergens: BTreeSet<String> = found
+        .iter()
+        .filter(|found| ALLERGENS.contains(&found.attribute.as_str()))
+        .map(|found| found.attribute.clone())
+        .collect();
+    let excluded_by = |diet: &[String]| -> Vec<&Found> {
+        found
+            .iter()
+            .filter(|found| diet.contains(&found.attribute))
+            .collect()
+    };
+    let diets = attributes
+        .diets
+        .iter()
+        .filter(|(_, excluded)| excluded_by(excluded).is_empty())
+        .map(|(diet, _)| diet.clone())
+        .collect();
+    let tags: Vec<String> = recipe
+        .metadata
+        .tags()
+        .into_iter()
+        .map(|tag| tag.to_lowercase().replace([' ', '_'], "-"))
+        .collect();
+    let contradictions = attributes
+        .diets
+        .iter()
+        .filter(|(diet, _)| tags.contains(diet))
+        .flat_map(|(diet, excluded)| {
+            excluded_by(excluded)
+                .into_iter()
+                .map(|found| Contradiction {
+                    diet: diet.clone(),
+                    ingredient: found.ingredient.clone(),
+                    attribute: found.attribute.clone(),
+                    recipe: found.recipe.clone(),
+                })
+        })
+        .collect();
+    DietReport {
+        allergens: allergens.into_iter().collect(),
+        diets,
+        contradictions,
+        unknown,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAPPING: &str = "# dairy
+butter: dairy
+peanut butter: peanuts
+flour: gluten
+walnuts: nuts
+tofu: soy
+diet keto: gluten, sugar
+";
+
+    #[test]
+    fn test_parse_attributes() {
+        let attributes = Attributes::parse(MAPPING).unwrap();
+        let normalizer = Normalizer::default();
+        assert_eq!(
+            attributes.get("Unsalted Butter", &normalizer, None),
+            Some(vec!["dairy"])
+        );
+        assert_eq!(
+            attributes.get("walnut", &normalizer, None),
+            Some(vec!["nuts"])
+        );
+        assert_eq!(
+            attributes.get("crunchy peanut butter", &normalizer, None),
+            Some(vec!["peanuts"])
+        );
+        assert_eq!(attributes.get("buttermilk", &normalizer, None), None);
+        assert!(attributes.diets.iter().any(|(diet, _)| diet == "keto"));
+        assert!(Attributes::parse("butter dairy").is_err());
+    }
+
+    #[test]
+    fn test_analyze() {
+        let attributes = Attributes::parse(MAPPING).unwrap();
+        let recipe = Recipe::try_from(
+            ">> tags: vegan, Gluten Free\nMix {flour}, {butter}, {walnuts} and {sugar}",
+        )
+        .unwrap();
+        let report = analyze(&recipe, None, &attributes, &Normalizer::default());
+        assert_eq!(report.allergens, vec!["dairy", "gluten", "nuts"]);
+        assert_eq!(report.diets, vec!["vegetarian", "pescatarian", "egg-free"]);
+        assert_eq!(report.unknown, vec!["sugar"]);
+        let contradictions: Vec<String> = report
+            .contradictions
+            .iter()
+            .map(DietReport::describe)
+            .collect();
+        assert_eq!(
+            contradictions,
+            vec![
+                "tagged vegan, but butter is dairy",
+                "tagged gluten-free, but flour is gluten"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_referenced_recipes() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/diet");
+        let path = dir.join("toast.recp");
+        let content = fs::read_to_string(&path).unwrap();
+        let recipe = Recipe::try_from(content.as_str()).unwrap();
+        let attributes = Attributes::parse(MAPPING).unwrap();
+        let report = analyze(&recipe, Some(&path), &attributes, &Normalizer::default());
+        assert_eq!(report.allergens, vec!["dairy"]);
+        assert_eq!(
+            DietReport::describe(&report.contradictions[0]),
+            "tagged vegan, but butter (from sauce) is dairy"
+        );
+    }
+
+    #[test]
+    fn test_unreadable_reference_is_unknown() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/diet");
+        let path = dir.join("crepe.recp");
+        let content = fs::read_to_string(&path).unwrap();
+        let recipe = Recipe::try_from(content.as_str()).unwrap();
+        let attributes = Attributes::parse(MAPPING).unwrap();
+        let report = analyze(&recipe, Some(&path), &attributes, &Normalizer::default());
+        assert!(report.unknown.contains(&"not-utf8".to_string()));
+    }
+}
@@ -0,0 +1,443 @@
+use recipe_parser::Token;
+use serde::Serialize;
+
+use crate::Recipe;
+use crate::metadata::normalize_key;
+use crate::names::Normalizer;
+use crate::recipe::Ingredient;
+use crate::units::{Dimension, format_quantity};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A metadata key whose values changed, repeated values are joined with commas
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetadataChange {
+    pub key: String,
+    pub kind: ChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// An ingredient added, removed, or used in another amount
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngredientChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    /// All the amounts of the ingredient, like `200 g + 1 tbsp`
+    pub old: Option<String>,
+    pub new: Option<String>,
+    /// Difference of the amounts when they can be added, like `+50 g`
+    pub delta: Option<String>,
+}
+
+/// A timer or a step added, removed or rewritten, positions start at 1
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListChange {
+    pub kind: ChangeKind,
+    pub old_position: Option<usize>,
+    pub new_position: Option<usize>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TextChange {
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// What changed between two versions of a recipe
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecipeDiff {
+    pub metadata: Vec<MetadataChange>,
+    pub ingredients: Vec<IngredientChange>,
+    pub timers: Vec<ListChange>,
+    pub steps: Vec<ListChange>,
+    pub backstory: Option<TextChange>,
+}
+
+impl RecipeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+            && self.ingredients.is_empty()
+            && self.timers.is_empty()
+            && self.steps.is_empty()
+            && self.backstory.is_none()
+    }
+}
+
+fn kind<T>(old: &Option<T>, new: &Option<T>) -> ChangeKind {
+    match (old, new) {
+        (None, _) => ChangeKind::Added,
+        (_, None) => ChangeKind::Removed,
+        _ => ChangeKind::Changed,
+    }
+}
+
+fn diff_metadata(old: &Recipe, new: &Recipe) -> Vec<MetadataChange> {
+    let mut keys: Vec<(String, &str)> = Vec::new();
+    for (key, _) in old.metadata.iter().chain(new.metadata.iter()) {
+        let normalized = normalize_key(key);
+        if !keys.iter().any(|(k, _)| *k == normalized) {
+            keys.push((normalized, key));
+        }
+    }
+    let mut changes = Vec::new();
+    for (_, key) in keys {
+        let value = |recipe: &Recipe| {
+            let values = recipe.metadata.get_all(key);
+            (!values.is_empty()).then(|| values.join(", "))
+        };
+        let (old, new) = (value(old), value(new));
+        if old == new {
+            continue;
+        }
+        changes.push(MetadataChange {
+            key: key.to_string(),
+            kind: kind(&old, &new),
+            old,
+            new,
+        });
+    }
+    changes
+}
+
+/// The uses of an ingredient in a recipe
+struct Uses<'a> {
+    key: String,
+    name: &'a str,
+    ingredients: Vec<&'a Ingredient>,
+}
+
+impl Uses<'_> {
+    fn amounts(&self) -> Option<String> {
+        let amounts: Vec<String> = self
+            .ingredients
+            .iter()
+            .filter_map(|ingredient| {
+                let quantity = ingredient.quantity.as_ref()?;
+                Some(match &ingredient.unit {
+                    Some(unit) => format!("{quantity} {unit}"),
+                    None => quantity.clone(),
+                })
+            })
+            .collect();
+        (!amounts.is_empty()).then(|| amounts.join(" + "))
+    }
+
+    /// The sum of the primary amounts, when they all have the same unit
+    fn total(&self) -> Option<(f64, String)> {
+        let unit = self.ingredients.first()?.unit.clone().unwrap_or_default();
+        let mut total = 0.0;
+        for ingredient in &self.ingredients {
+            if !ingredient
+                .unit
+                .as_deref()
+                .unwrap_or_default()
+                .eq_ignore_ascii_case(&unit)
+            {
+                return None;
+            }
+            total += ingredient.amount().value()?;
+        }
+        Some((total, unit))
+    }
+
+    /// The sum of the primary amounts in the base unit of their dimension
+    fn base_total(&self) -> Option<(f64, String)> {
+        let mut bases = self
+            .ingredients
+            .iter()
+            .map(|ingredient| ingredient.amount().to_base());
+        let (mut total, dimension) = bases.next()??;
+        for base in bases {
+            let (value, other) = base?;
+            if other != dimension {
+                return None;
+            }
+            total += value;
+        }
+        let unit = match dimension {
+            Dimension::Mass => "g",
+            Dimension::Volume => "ml",
+            Dimension::Count => "",
+        };
+        Some((total, unit.to_string()))
+    }
+}
+
+fn uses<'a>(recipe: &'a Recipe, normalizer: &Normalizer) -> Vec<Uses<'a>> {
+    let mut uses: Vec<Uses> = Vec::new();
+    for ingredient in recipe.ingredients.iter().chain(&recipe.recipes_refs) {
+        let key = recipe.normalized_name(&ingredient.name, normalizer);
+        match uses.iter_mut().find(|uses| uses.key == key) {
+            Some(uses) => uses.ingredients.push(ingredient),
+            None => uses.push(Uses {
+                key,
+                name: &ingredient.name,
+                ingredients: vec![ingredient],
+            }),
+        }
+    }
+    uses
+}
+
+/// Difference between two totals, in their unit or in the base unit, e.g: `+50 g`
+fn delta(old: &Uses, new: &Uses) -> Option<String> {
+    let (old, new, new_unit) = match (old.total(), new.total()) {
+        (Some((old, old_unit)), Some((new, new_unit)))
+            if old_unit.eq_ignore_ascii_case(&new_unit) =>
+        {
+            (old, new, new_unit)
+        }
+        _ => {
+            let (old, old_unit) = old.base_total()?;
+            let (new, new_unit) = new.base_total()?;
+            if old_unit != new_unit {
+                return None;
+            }
+            (old, new, new_unit)
+        }
+    };
+    let difference = new - old;
+    let sign = if difference < 0.0 { "-" } else { "+" };
+    Some(
+        format!("{sign}{} {new_unit}", format_quantity(difference.abs()))
+            .trim()
+            .to_string(),
+    )
+}
+
+fn diff_ingredients(old: &Recipe, new: &Recipe, normalizer: &Normalizer) -> Vec<IngredientChange> {
+    let old_uses = uses(old, normalizer);
+    let new_uses = uses(new, normalizer);
+    let mut changes = Vec::new();
+    for old_use in &old_uses {
+        match new_uses.iter().find(|new_use| new_use.key == old_use.key) {
+            None => changes.push(IngredientChange {
+                name: old_use.name.to_string(),
+                kind: ChangeKind::Removed,
+                old: old_use.amounts(),
+                new: None,
+                delta: None,
+            }),
+            Some(new_use) if old_use.amounts() != new_use.amounts() => {
+                changes.push(IngredientChange {
+                    name: new_use.name.to_string(),
+                    kind: ChangeKind::Changed,
+                    old: old_use.amounts(),
+                    new: new_use.amounts(),
+                    delta: delta(old_use, new_use),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for new_use in &new_uses {
+        if !old_uses.iter().any(|old_use| old_use.key == new_use.key) {
+            changes.push(IngredientChange {
+                name: new_use.name.to_string(),
+                kind: ChangeKind::Added,
+                old: None,
+                new: new_use.amounts(),
+                delta: None,
+            });
+        }
+    }
+    changes
+}
+
+/// Longest common subsequence diff, the removed and added items between two
+/// common ones are paired as changes
+fn diff_lists(old: &[String], new: &[String]) -> Vec<ListChange> {
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut changes = Vec::new();
+    let (mut removed, mut added): (Vec<usize>, Vec<usize>) = (vec![], vec![]);
+    let mut flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+        for pair in 0..removed.len().max(added.len()) {
+            let (old_position, new_position) = (removed.get(pair), added.get(pair));
+            let old_item = old_position.map(|i| old[*i].clone());
+            let new_item = new_position.map(|j| new[*j].clone());
+            changes.push(ListChange {
+                kind: kind(&old_item, &new_item),
+                old_position: old_position.map(|i| i + 1),
+                new_position: new_position.map(|j| j + 1),
+                old: old_item,
+                new: new_item,
+            });
+        }
+        removed.clear();
+        added.clear();
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            flush(&mut removed, &mut added);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+    flush(&mut removed, &mut added);
+    changes
+}
+
+/// The paragraphs of the instructions, as plain text
+pub fn steps(recipe: &Recipe) -> Vec<String> {
+    let text: String = recipe
+        .instructions
+        .iter()
+        .map(|token| match token {
+            Token::Section(name) => format!("== {name} =="),
+            Token::Backstory(_) => String::new(),
+            token => token.to_string(),
+        })
+        .collect();
+    text.split("\n\n")
+        .map(|step| step.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|step| !step.is_empty())
+        .collect()
+}
+
+/// Compare two versions of a recipe.
+///
+/// Ingredients are matched by their normalized name, and all their uses are added up.
+/// Timers and steps are compared in order.
+pub fn diff(old: &Recipe, new: &Recipe, normalizer: &Normalizer) -> RecipeDiff {
+    let timers = |recipe: &Recipe| -> Vec<String> {
+        recipe
+            .timers
+            .iter()
+            .map(|timer| timer.duration.clone())
+            .collect()
+    };
+    let backstory = |recipe: &Recipe| {
+        recipe
+            .backstory
+            .as_ref()
+            .map(|backstory| backstory.trim().to_string())
+            .filter(|backstory| !backstory.is_empty())
+    };
+    let (old_backstory, new_backstory) = (backstory(old), backstory(new));
+    RecipeDiff {
+        metadata: diff_metadata(old, new),
+        ingredients: diff_ingredients(old, new, normalizer),
+        timers: diff_lists(&timers(old), &timers(new)),
+        steps: diff_lists(&steps(old), &steps(new)),
+        backstory: (old_backstory != new_backstory).then_some(TextChange {
+            old: old_backstory,
+            new: new_backstory,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recipe_diff(old: &str, new: &str) -> RecipeDiff {
+        let old = Recipe::try_from(old).unwrap();
+        let new = Recipe::try_from(new).unwrap();
+        diff(&old, &new, &Normalizer::default())
+    }
+
+    #[test]
+    fn test_same_recipe() {
+        let recipe = ">> servings: 2\nMix {flour}(200 g)\n\nBake t{20 minutes}";
+        assert!(recipe_diff(recipe, recipe).is_empty());
+    }
+
+    #[test]
+    fn test_diff_ingredients() {
+        let diff = recipe_diff(
+            "Mix {flour}(200 g), {butter}(1 tbsp) and {eggs}(2)",
+            "Mix {Flour}(250 g), {sugar}(1 cup) and {egg}(2)",
+        );
+        let changes: Vec<_> = diff
+            .ingredients
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind, c.delta.as_deref()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("Flour", ChangeKind::Changed, Some("+50 g")),
+                ("butter", ChangeKind::Removed, None),
+                ("sugar", ChangeKind::Added, None),
+            ]
+        );
+        assert_eq!(diff.ingredients[0].old.as_deref(), Some("200 g"));
+    }
+
+    #[test]
+    fn test_diff_delta_in_base_unit() {
+        let diff = recipe_diff(
+            "Add {milk}(1 cup)",
+            "Add {milk}(200 ml) and more {milk}(2 cl)",
+        );
+        assert_eq!(diff.ingredients[0].new.as_deref(), Some("200 ml + 2 cl"));
+        assert_eq!(diff.ingredients[0].delta.as_deref(), Some("-20 ml"));
+    }
+
+    #[test]
+    fn test_diff_metadata_timers_and_steps() {
+        let diff = recipe_diff(
+            ">> servings: 2\n>> tags: easy\nMix {flour}\n\nBake t{20 minutes}\n\nServe\n---\nA story.",
+            ">> servings: 4\n>> lang: en\nMix {flour}\n\nBake t{25 minutes}\n\nLet it cool\n\nServe",
+        );
+        let metadata: Vec<_> = diff
+            .metadata
+            .iter()
+            .map(|c| (c.key.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            metadata,
+            vec![
+                ("servings", ChangeKind::Changed),
+                ("tags", ChangeKind::Removed),
+                ("lang", ChangeKind::Added)
+            ]
+        );
+        assert_eq!(diff.timers[0].old.as_deref(), Some("20 minutes"));
+        assert_eq!(diff.timers[0].new.as_deref(), Some("25 minutes"));
+        let steps: Vec<_> = diff
+            .steps
+            .iter()
+            .map(|c| (c.kind, c.old_position, c.new_position))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (ChangeKind::Changed, Some(2), Some(2)),
+                (ChangeKind::Added, None, Some(3))
+            ]
+        );
+        assert_eq!(
+            diff.backstory,
+            Some(TextChange {
+                old: Some("A story.".to_string()),
+                new: None
+            })
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use recipe_parser::{FrontMatterFormat, ParseOptions, SPEC_VERSION, Token, parse_with};
+
+/// A value of the front matter, independent of the format it was written in
+enum Value {
+    Scalar(String),
+    List(Vec<Value>),
+    Table(Vec<(String, Value)>),
+}
+
+impl From<serde_yaml_ng::Value> for Value {
+    fn from(value: serde_yaml_ng::Value) -> Self {
+        use serde_yaml_ng::Value as Yaml;
+        match value {
+            Yaml::Null => Value::List(vec![]),
+            Yaml::Bool(v) => Value::Scalar(v.to_string()),
+            Yaml::Number(v) => Value::Scalar(v.to_string()),
+            Yaml::String(v) => Value::Scalar(v),
+            Yaml::Sequence(values) => Value::List(values.into_iter().map(Value::from).collect()),
+            Yaml::Mapping(mapping) => Value::Table(
+                mapping
+                    .into_iter()
+                    .filter_map(|(key, value)| match Value::from(key) {
+                        Value::Scalar(key) => Some((key, value.into())),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            Yaml::Tagged(tagged) => tagged.value.into(),
+        }
+    }
+}
+
+impl From<toml::Value> for Value {
+    fn from(value: toml::Value) -> Self {
+        use toml::Value as Toml;
+        match value {
+            Toml::String(v) => Value::Scalar(v),
+            Toml::Integer(v) => Value::Scalar(v.to_string()),
+            Toml::Float(v) => Value::Scalar(v.to_string()),
+            Toml::Boolean(v) => Value::Scalar(v.to_string()),
+            Toml::Datetime(v) => Value::Scalar(v.to_string()),
+            Toml::Array(values) => Value::List(values.into_iter().map(Value::from).collect()),
+            Toml::Table(table) => Value::Table(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Lists become repeated keys and nested tables use dotted keys,
+/// e.g: `nutrition.calories`. Empty values are skipped.
+fn flatten(key: &str, value: Value, entries: &mut Vec<(String, String)>) {
+    match value {
+        Value::Scalar(value) => entries.push((key.to_string(), value)),
+        Value::List(values) => {
+            for value in values {
+                flatten(key, value, entries);
+            }
+        }
+        Value::Table(table) => {
+            for (child, value) in table {
+                let child = if key.is_empty() {
+                    child
+                } else {
+                    format!("{key}.{child}")
+                };
+                flatten(&child, value, entries);
+            }
+        }
+    }
+}
+
+/// Read the front matter as metadata entries, in the order they were written
+pub(crate) fn entries(
+    format: FrontMatterFormat,
+    content: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let value: Value = match format {
+        FrontMatterFormat::Yaml => serde_yaml_ng::from_str::<serde_yaml_ng::Value>(content)
+            .map_err(|err| format!("invalid YAML front matter: {err}"))?
+            .into(),
+        FrontMatterFormat::Toml => toml::from_str::<toml::Table>(content)
+            .map_err(|err| format!("invalid TOML front matter: {err}"))
+            .map(toml::Value::Table)?
+            .into(),
+    };
+    // an empty front matter is read by YAML as null, which is an empty list
+    if !matches!(&value, Value::Table(_)) && !matches!(&value, Value::List(v) if v.is_empty()) {
+        return Err("the front matter must contain keys and values".to_string());
+    }
+    let mut entries = Vec::new();
+    flatten("", value, &mut entries);
+    Ok(entries)
+}
+
+/// The tokens of a recipe, parsed again without front matter when the block at the
+/// beginning is not YAML or TOML keys, so its fences are part of the recipe like
+/// before front matter was supported
+pub(crate) fn without_invalid_front_matter<'a>(
+    input: &'a str,
+    tokens: Vec<Token<'a>>,
+) -> Result<Vec<Token<'a>>, String> {
+    match tokens.first() {
+        Some(Token::FrontMatter { format, content }) if entries(*format, content).is_err() => {
+            let options = ParseOptions {
+                front_matter: false,
+                version: Some(SPEC_VERSION),
+                ..Default::default()
+            };
+            parse_with(input, &options)
+                .map(|parsed| parsed.tokens.into_iter().map(|(token, _)| token).collect())
+                .map_err(|err| err.to_string())
+        }
+        _ => Ok(tokens),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_yaml_entries() {
+        let content =
+            "name: hummus\nservings: 2\ntags: [vegan, easy]\nnutrition:\n  calories: 300\nempty:";
+        let entries = entries(FrontMatterFormat::Yaml, content).unwrap();
+        let expected = [
+            ("name", "hummus"),
+            ("servings", "2"),
+            ("tags", "vegan"),
+            ("tags", "easy"),
+            ("nutrition.calories", "300"),
+        ];
+        assert_eq!(
+            entries,
+            expected.map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_toml_entries() {
+        let content = "name = \"hummus\"\ntags = [\"vegan\"]\n[nutrition]\ncalories = 300";
+        let entries = entries(FrontMatterFormat::Toml, content).unwrap();
+        let expected = [
+            ("name", "hummus"),
+            ("tags", "vegan"),
+            ("nutrition.calories", "300"),
+        ];
+        assert_eq!(
+            entries,
+            expected.map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_front_matter() {
+        assert!(entries(FrontMatterFormat::Yaml, "name: [hummus").is_err());
+        assert!(entries(FrontMatterFormat::Yaml, "just text").is_err());
+        assert!(entries(FrontMatterFormat::Yaml, "- hummus").is_err());
+        assert!(entries(FrontMatterFormat::Yaml, "").unwrap().is_empty());
+        assert!(entries(FrontMatterFormat::Toml, "name: hummus").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use recipe_parser::Token;
+
+use crate::Recipe;
+use crate::names::Normalizer;
+use crate::units::parse_duration;
+
+/// Name of the index file written by `recp index`
+pub const INDEX_FILE: &str = ".recp-index.json";
+
+/// Extension of the recipe files
+pub const RECIPE_EXTENSION: &str = "recp";
+
+/// The sum of the timers of a recipe, for recipes without times in the metadata
+fn timers_time(recipe: &Recipe) -> Option<Duration> {
+    recipe
+        .timers
+        .iter()
+        .filter_map(|timer| parse_duration(&timer.duration))
+        .reduce(|total, time| total + time)
+}
+
+/// What is kept of a recipe to search it without parsing it again
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    pub ingredients: Vec<String>,
+    pub materials: Vec<String>,
+    pub lang: Option<String>,
+    /// Total time in seconds
+    pub total_time: Option<u64>,
+    /// The instructions and backstory as plain text
+    pub text: String,
+}
+
+impl IndexEntry {
+    pub fn new(path: impl Into<PathBuf>, recipe: &Recipe) -> Self {
+        let mut text: String = recipe
+            .instructions
+            .iter()
+            .filter(|token| !matches!(token, Token::Backstory(_)))
+            .map(|token| token.to_string())
+            .collect();
+        if let Some(backstory) = &recipe.backstory {
+            text.push('\n');
+            text.push_str(backstory);
+        }
+        let mut ingredients: Vec<String> = Vec::new();
+        for ingredient in &recipe.ingredients {
+            if !ingredients.contains(&ingredient.name) {
+                ingredients.push(ingredient.name.clone());
+            }
+        }
+        Self {
+            path: path.into(),
+            name: recipe.name.clone(),
+            tags: recipe
+                .metadata
+                .tags()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            ingredients,
+            materials: recipe
+                .materials
+                .iter()
+                .map(|material| material.name.clone())
+                .collect(),
+            lang: recipe.metadata.lang().map(str::to_string),
+            total_time: recipe
+                .metadata
+                .total_time()
+                .or_else(|| timers_time(recipe))
+                .map(|time| time.as_secs()),
+            text: text.trim().to_string(),
+        }
+    }
+
+    pub fn total_time(&self) -> Option<Duration> {
+        self.total_time.map(Duration::from_secs)
+    }
+
+    /// The name of the recipe, or the file name when it has no name
+    pub fn title(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// A file that couldn't be indexed
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// All the recipes found in a directory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    pub entries: Vec<IndexEntry>,
+}
+
+/// Find all the recipe files in a directory, sorted by path
+pub fn recipe_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == RECIPE_EXTENSION))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Find the file of a `@{}` reference, relative to the directory of the recipe using it
+pub fn resolve_ref(dir: &Path, name: &str) -> Option<PathBuf> {
+    [
+        dir.join(format!("{name}.{RECIPE_EXTENSION}")),
+        dir.join(name),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+impl Index {
+    /// Parse every recipe in the directory, the files that fail are returned apart
+    pub fn build(dir: &Path) -> (Index, Vec<IndexError>) {
+        let mut index = Index::default();
+        let mut errors = Vec::new();
+        for path in recipe_files(dir) {
+            let entry = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|content| {
+                    Recipe::try_from(content.as_str()).map(|recipe| IndexEntry::new(&path, &recipe))
+                });
+            match entry {
+                Ok(entry) => index.entries.push(entry),
+                Err(reason) => errors.push(IndexError { path, reason }),
+            }
+        }
+        (index, errors)
+    }
+
+    pub fn load(path: &Path) -> Result<Index, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("invalid index {}: {err}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| format!("could not write {}: {err}", path.display()))
+    }
+
+    /// The entries matching all the conditions of the query
+    pub fn search(&self, query: &Query, normalizer: &Normalizer) -> Vec<&IndexEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| query.matches(entry, normalizer))
+            .collect()
+    }
+}
+
+/// Conditions a recipe must meet, all of them are required.
+///
+/// Text comparisons ignore case. Ingredients and materials are compared by their
+/// normalized names, and match when they contain the searched words:
+/// `tofu` matches `smoked tofu`, and `tomatoes` matches `tomato sauce`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub ingredients: Vec<String>,
+    pub without_ingredients: Vec<String>,
+    pub tags: Vec<String>,
+    pub materials: Vec<String>,
+    pub without_materials: Vec<String>,
+    pub max_time: Option<Duration>,
+    pub lang: Option<String>,
+    /// Words that must appear in the name or the text
+    pub text: Option<String>,
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack
+        .to_lowercase()
+        .contains(&needle.trim().to_lowercase())
+}
+
+impl Query {
+    pub fn matches(&self, entry: &IndexEntry, normalizer: &Normalizer) -> bool {
+        let lang = entry.lang.as_deref();
+        let any_contains = |names: &[String], needle: &str| {
+            let needle = format!(" {} ", normalizer.normalize(needle, lang));
+            names
+                .iter()
+                .any(|name| format!(" {} ", normalizer.normalize(name, lang)).contains(&needle))
+        };
+        let lang_matches = self.lang.as_ref().is_none_or(|lang| {
+            // `en` matches `en-US`
+            entry.lang.as_ref().is_some_and(|entry_lang| {
+                let entry_lang = entry_lang.to_lowercase();
+                let lang = lang.to_lowercase();
+                entry_lang == lang || entry_lang.starts_with(&format!("{lang}-"))
+            })
+        });
+        let text_matches = self.text.as_ref().is_none_or(|text| {
+            let haystack = format!("{}\n{}", entry.title(), entry.text);
+            text.split_whitespace()
+                .all(|word| contains(&haystack, word))
+        });
+        self.ingredients
+            .iter()
+            .all(|name| any_contains(&entry.ingredients, name))
+            && !self
+                .without_ingredients
+                .iter()
+                .any(|name| any_contains(&entry.ingredients, name))
+            && self.tags.iter().all(|tag| {
+                entry
+                    .tags
+                    .iter()
+                    .any(|entry_tag| entry_tag.eq_ignore_ascii_case(tag.trim()))
+            })
+            && self
+                .materials
+                .iter()
+                .all(|name| any_contains(&entry.materials, name))
+            && !self
+                .without_materials
+                .iter()
+                .any(|name| any_contains(&entry.materials, name))
+            && self
+                .max_time
+                .is_none_or(|max_time| entry.total_time().is_some_and(|time| time <= max_time))
+            && lang_matches
+            && text_matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(content: &str) -> IndexEntry {
+        IndexEntry::new("recipe.recp", &Recipe::try_from(content).unwrap())
+    }
+
+    #[test]
+    fn test_index_entry() {
+        let entry = entry(
+            ">> name: tofu bowl\n>> tags: vegan, easy\n>> lang: en-US\n>> total-time: 25 minutes\nFry the {smoked tofu}(200 gr) in a &{pan}\n---\nfrom a friend",
+        );
+        assert_eq!(entry.title(), "tofu bowl");
+        assert_eq!(entry.tags, vec!["vegan", "easy"]);
+        assert_eq!(entry.ingredients, vec!["smoked tofu"]);
+        assert_eq!(entry.materials, vec!["pan"]);
+        assert_eq!(entry.total_time, Some(1500));
+        assert_eq!(entry.text, "Fry the smoked tofu in a pan\nfrom a friend");
+    }
+
+    #[test]
+    fn test_total_time_from_timers() {
+        let timers = entry("Fry for t{5 minutes}, rest t{until cold} and bake t{1 hour}");
+        assert_eq!(timers.total_time, Some(65 * 60));
+        let metadata = entry(">> total-time: 20 minutes\nBake for t{1 hour}");
+        assert_eq!(metadata.total_time, Some(20 * 60));
+        assert_eq!(entry("Bake until golden").total_time, None);
+    }
+
+    #[test]
+    fn test_search() {
+        let index = Index {
+            entries: vec![
+                entry(
+                    ">> name: tofu bowl\n>> tags: vegan\n>> lang: en-US\n>> total-time: 25 minutes\nFry the {tofu} in a &{pan}",
+                ),
+                entry(
+                    ">> name: tofu shake\n>> tags: vegan\n>> total-time: 5 minutes\nBlend {silken tofu} with a &{blender}",
+                ),
+                entry(">> name: omelette\n>> total-time: 10 minutes\nBeat the {eggs} in a &{bowl}"),
+            ],
+        };
+        let names = |query: &Query| -> Vec<String> {
+            index
+                .search(query, &Normalizer::default())
+                .into_iter()
+                .map(|entry| entry.title())
+                .collect()
+        };
+        let query = Query {
+            ingredients: vec!["Tofu".to_string()],
+            tags: vec!["vegan".to_string()],
+            without_materials: vec!["blender".to_string()],
+            max_time: Some(Duration::from_secs(30 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(names(&query), vec!["tofu bowl"]);
+
+        let query = Query {
+            max_time: Some(Duration::from_secs(10 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(names(&query), vec!["tofu shake", "omelette"]);
+
+        let query = Query {
+            lang: Some("en".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(names(&query), vec!["tofu bowl"]);
+
+        let query = Query {
+            text: Some("beat EGGS".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(names(&query), vec!["omelette"]);
+
+        let query = Query {
+            ingredients: vec!["Egg".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(names(&query), vec!["omelette"]);
+    }
+}
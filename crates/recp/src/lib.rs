@@ -0,0 +1,21 @@
+//! Recipe model used by the `recp` cli.
+//!
+//! Recipes are parsed with [recipe_parser] into tokens, which are then
+//! collected into a [Recipe].
+pub mod cost;
+pub mod diet;
+pub mod diff;
+#[cfg(feature = "front-matter")]
+mod front_matter;
+pub mod index;
+pub mod locale;
+pub mod merge;
+pub mod metadata;
+pub mod names;
+pub mod nutrition;
+pub mod pantry;
+pub mod recipe;
+pub mod translate;
+pub mod units;
+
+pub use recipe::Recipe;
@@ -0,0 +1,388 @@
+use std::time::Duration;
+
+use crate::units::format_quantity;
+
+/// Words and number formats of a language, from the tables bundled with recp.
+///
+/// The `lang` metadata selects the locale of a recipe, english is the default.
+#[derive(Debug, Clone, Copy)]
+pub struct Locale {
+    /// Primary subtag of the language, like `es` for `es-AR`
+    pub lang: &'static str,
+    /// `1,5` is one and a half and `1.000` is a thousand, instead of `1.5` and `1,000`
+    pub decimal_comma: bool,
+    /// Names of the units by their symbol, e.g: `("g", &["gramo", "gramos"])`
+    pub units: &'static [(&'static str, &'static [&'static str])],
+    /// Names of the time units by their length in seconds
+    pub time_units: &'static [(f64, &'static [&'static str])],
+    /// The word joining the parts of a duration, like `and` in `1 hour and 30 minutes`
+    pub and: &'static str,
+    /// Written between the parts of a duration
+    pub space: &'static str,
+    /// Singular and plural patterns, `{}` is replaced by the number
+    pub hours: (&'static str, &'static str),
+    pub minutes: (&'static str, &'static str),
+    pub servings: (&'static str, &'static str),
+    /// Section labels of the renderers
+    pub ingredients: &'static str,
+    pub instructions: &'static str,
+    pub diet: &'static str,
+    /// Labels of the diet section: allergens, the diets the recipe fits, and the
+    /// ingredients the diets couldn't check
+    pub allergens: &'static str,
+    pub suitable_for: &'static str,
+    pub unchecked: &'static str,
+    /// Plural rule, `true` when the singular is used for the number
+    singular: fn(f64) -> bool,
+}
+
+fn singular_one(count: f64) -> bool {
+    count == 1.0
+}
+
+/// French uses the singular for 0 and 1, and everything below 2
+fn singular_below_two(count: f64) -> bool {
+    count < 2.0
+}
+
+pub const LOCALES: &[Locale] = &[
+    Locale {
+        lang: "en",
+        decimal_comma: false,
+        units: &[],
+        time_units: &[
+            (1.0, &["s", "sec", "secs", "second", "seconds"]),
+            (60.0, &["m", "min", "mins", "minute", "minutes"]),
+            (3600.0, &["h", "hr", "hrs", "hour", "hours"]),
+            (86400.0, &["d", "day", "days"]),
+        ],
+        and: "and",
+        space: " ",
+        hours: ("{} hour", "{} hours"),
+        minutes: ("{} minute", "{} minutes"),
+        servings: ("{} serving", "{} servings"),
+        ingredients: "Ingredients",
+        instructions: "Instructions",
+        diet: "Diet",
+        allergens: "Allergens",
+        suitable_for: "Suitable for",
+        unchecked: "Not checked",
+        singular: singular_one,
+    },
+    Locale {
+        lang: "es",
+        decimal_comma: true,
+        units: &[
+            ("g", &["gramo", "gramos"]),
+            ("kg", &["kilogramo", "kilogramos"]),
+            ("ml", &["mililitro", "mililitros"]),
+            ("l", &["litro", "litros"]),
+            ("tsp", &["cucharadita", "cucharaditas"]),
+            ("tbsp", &["cucharada", "cucharadas"]),
+            ("cup", &["taza", "tazas"]),
+        ],
+        time_units: &[
+            (1.0, &["segundo", "segundos", "seg"]),
+            (60.0, &["minuto", "minutos"]),
+            (3600.0, &["hora", "horas"]),
+            (86400.0, &["día", "días", "dia", "dias"]),
+        ],
+        and: "y",
+        space: " ",
+        hours: ("{} hora", "{} horas"),
+        minutes: ("{} minuto", "{} minutos"),
+        servings: ("{} porción", "{} porciones"),
+        ingredients: "Ingredientes",
+        instructions: "Instrucciones",
+        diet: "Dieta",
+        allergens: "Alérgenos",
+        suitable_for: "Apto para",
+        unchecked: "Sin verificar",
+        singular: singular_one,
+    },
+    Locale {
+        lang: "pt",
+        decimal_comma: true,
+        units: &[
+            ("g", &["grama", "gramas"]),
+            ("kg", &["quilo", "quilos", "quilograma", "quilogramas"]),
+            ("ml", &["mililitro", "mililitros"]),
+            ("l", &["litro", "litros"]),
+            ("tsp", &["colher de chá", "colheres de chá"]),
+            ("tbsp", &["colher de sopa", "colheres de sopa"]),
+            ("cup", &["xícara", "xícaras", "chávena", "chávenas"]),
+        ],
+        time_units: &[
+            (1.0, &["segundo", "segundos", "seg"]),
+            (60.0, &["minuto", "minutos"]),
+            (3600.0, &["hora", "horas"]),
+            (86400.0, &["dia", "dias"]),
+        ],
+        and: "e",
+        space: " ",
+        hours: ("{} hora", "{} horas"),
+        minutes: ("{} minuto", "{} minutos"),
+        servings: ("{} porção", "{} porções"),
+        ingredients: "Ingredientes",
+        instructions: "Modo de preparo",
+        diet: "Dieta",
+        allergens: "Alergênicos",
+        suitable_for: "Adequado para",
+        unchecked: "Não verificados",
+        singular: singular_one,
+    },
+    Locale {
+        lang: "fr",
+        decimal_comma: true,
+        units: &[
+            ("kg", &["kilogramme", "kilogrammes"]),
+            ("tsp", &["cuillère à café", "cuillères à café", "càc"]),
+            ("tbsp", &["cuillère à soupe", "cuillères à soupe", "càs"]),
+            ("cup", &["tasse", "tasses"]),
+        ],
+        time_units: &[
+            (1.0, &["seconde", "secondes"]),
+            (60.0, &["minute", "minutes"]),
+            (3600.0, &["heure", "heures"]),
+            (86400.0, &["jour", "jours"]),
+        ],
+        and: "et",
+        space: " ",
+        hours: ("{} heure", "{} heures"),
+        minutes: ("{} minute", "{} minutes"),
+        servings: ("{} portion", "{} portions"),
+        ingredients: "Ingrédients",
+        instructions: "Préparation",
+        diet: "Régime",
+        allergens: "Allergènes",
+        suitable_for: "Convient pour",
+        unchecked: "Non vérifiés",
+        singular: singular_below_two,
+    },
+    Locale {
+        lang: "de",
+        decimal_comma: true,
+        units: &[
+            ("g", &["gramm"]),
+            ("kg", &["kilogramm"]),
+            ("ml", &["milliliter"]),
+            ("tsp", &["teelöffel", "tl"]),
+            ("tbsp", &["esslöffel", "el"]),
+            ("cup", &["tasse", "tassen"]),
+        ],
+        time_units: &[
+            (1.0, &["sekunde", "sekunden", "sek"]),
+            (60.0, &["minute", "minuten"]),
+            (3600.0, &["stunde", "stunden", "std"]),
+            (86400.0, &["tag", "tage"]),
+        ],
+        and: "und",
+        space: " ",
+        hours: ("{} Stunde", "{} Stunden"),
+        minutes: ("{} Minute", "{} Minuten"),
+        servings: ("{} Portion", "{} Portionen"),
+        ingredients: "Zutaten",
+        instructions: "Zubereitung",
+        diet: "Ernährung",
+        allergens: "Allergene",
+        suitable_for: "Geeignet für",
+        unchecked: "Nicht geprüft",
+        singular: singular_one,
+    },
+    Locale {
+        lang: "ja",
+        decimal_comma: false,
+        units: &[
+            ("g", &["グラム"]),
+            ("kg", &["キロ", "キログラム"]),
+            ("ml", &["ミリリットル"]),
+            ("l", &["リットル"]),
+            ("tsp", &["小さじ"]),
+            ("tbsp", &["大さじ"]),
+        ],
+        time_units: &[
+            (1.0, &["秒"]),
+            (60.0, &["分"]),
+            (3600.0, &["時間"]),
+            (86400.0, &["日"]),
+        ],
+        and: "",
+        space: "",
+        hours: ("{}時間", "{}時間"),
+        minutes: ("{}分", "{}分"),
+        servings: ("{}人分", "{}人分"),
+        ingredients: "材料",
+        instructions: "作り方",
+        diet: "食事",
+        allergens: "アレルゲン",
+        suitable_for: "対応",
+        unchecked: "未確認",
+        singular: singular_one,
+    },
+    Locale {
+        lang: "zh",
+        decimal_comma: false,
+        units: &[
+            ("g", &["克"]),
+            ("kg", &["千克", "公斤"]),
+            ("ml", &["毫升"]),
+            ("l", &["升"]),
+            ("tsp", &["茶匙"]),
+            ("tbsp", &["汤匙", "湯匙"]),
+        ],
+        time_units: &[
+            (1.0, &["秒"]),
+            (60.0, &["分", "分钟", "分鐘"]),
+            (3600.0, &["小时", "小時"]),
+            (86400.0, &["天"]),
+        ],
+        and: "",
+        space: "",
+        hours: ("{}小时", "{}小时"),
+        minutes: ("{}分钟", "{}分钟"),
+        servings: ("{}人份", "{}人份"),
+        ingredients: "食材",
+        instructions: "做法",
+        diet: "饮食",
+        allergens: "过敏原",
+        suitable_for: "适合",
+        unchecked: "未检查",
+        singular: singular_one,
+    },
+];
+
+impl Locale {
+    /// The locale of a BCP 47 tag, english when the language is not bundled
+    pub fn get(lang: Option<&str>) -> &'static Locale {
+        let primary = lang
+            .and_then(|lang| lang.split(['-', '_']).next())
+            .unwrap_or("en")
+            .to_lowercase();
+        LOCALES
+            .iter()
+            .find(|locale| locale.lang == primary)
+            .unwrap_or(&LOCALES[0])
+    }
+
+    /// Remove the thousands separators of a quantity, e.g: `1.000,5` -> `1000.5` in
+    /// spanish and `1,000` -> `1000` in english.
+    ///
+    /// A separator only groups when it's followed by groups of 3 digits, so `1,5`
+    /// is one and a half in all languages.
+    pub fn normalize_quantity(&self, quantity: &str) -> String {
+        let (group, decimal) = if self.decimal_comma {
+            ('.', ',')
+        } else {
+            (',', '.')
+        };
+        let (integer, fraction) = match quantity.trim().split_once(decimal) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (quantity.trim(), None),
+        };
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        let mut groups = integer.split(group);
+        let first = groups.next().unwrap_or_default();
+        let rest: Vec<&str> = groups.collect();
+        let grouped = !rest.is_empty()
+            && (1..=3).contains(&first.len())
+            && is_digits(first)
+            && rest.iter().all(|part| part.len() == 3 && is_digits(part));
+        if !grouped {
+            return quantity.to_string();
+        }
+        let integer = integer.replace(group, "");
+        match fraction {
+            Some(fraction) => format!("{integer}.{fraction}"),
+            None => integer,
+        }
+    }
+
+    /// Fill the singular or plural pattern for the number
+    pub fn plural(&self, count: f64, (singular, plural): (&str, &str)) -> String {
+        let pattern = if (self.singular)(count) {
+            singular
+        } else {
+            plural
+        };
+        pattern.replace("{}", &self.format_quantity(count))
+    }
+
+    /// Format a number without trailing zeros, with a decimal comma when the language uses it
+    pub fn format_quantity(&self, value: f64) -> String {
+        let formatted = format_quantity(value);
+        if self.decimal_comma {
+            formatted.replace('.', ",")
+        } else {
+            formatted
+        }
+    }
+
+    /// A duration in words, like `1 hour 30 minutes` or `1時間30分`
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let minutes = duration.as_secs().div_ceil(60);
+        let (hours, minutes) = (minutes / 60, minutes % 60);
+        let mut parts = Vec::new();
+        if hours > 0 {
+            parts.push(self.plural(hours as f64, self.hours));
+        }
+        if minutes > 0 || hours == 0 {
+            parts.push(self.plural(minutes as f64, self.minutes));
+        }
+        parts.join(self.space)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_locale() {
+        assert_eq!(Locale::get(Some("es-AR")).lang, "es");
+        assert_eq!(Locale::get(Some("PT_br")).lang, "pt");
+        assert_eq!(Locale::get(Some("sw")).lang, "en");
+        assert_eq!(Locale::get(None).lang, "en");
+    }
+
+    #[test]
+    fn test_locales_have_every_time_unit() {
+        for locale in LOCALES {
+            let lengths: Vec<f64> = locale.time_units.iter().map(|(s, _)| *s).collect();
+            assert_eq!(lengths, vec![1.0, 60.0, 3600.0, 86400.0], "{}", locale.lang);
+        }
+    }
+
+    #[test]
+    fn test_normalize_quantity() {
+        let en = Locale::get(Some("en"));
+        let es = Locale::get(Some("es"));
+        assert_eq!(en.normalize_quantity("1,000"), "1000");
+        assert_eq!(en.normalize_quantity("1,000.5"), "1000.5");
+        assert_eq!(en.normalize_quantity("1,5"), "1,5");
+        assert_eq!(es.normalize_quantity("1.000"), "1000");
+        assert_eq!(es.normalize_quantity("1.000,5"), "1000.5");
+        assert_eq!(es.normalize_quantity("1.5"), "1.5");
+        assert_eq!(es.normalize_quantity("1/2"), "1/2");
+    }
+
+    #[test]
+    fn test_plural_and_durations() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        let locale = |lang| Locale::get(Some(lang));
+        assert_eq!(
+            locale("en").format_duration(minutes(90)),
+            "1 hour 30 minutes"
+        );
+        assert_eq!(locale("es").format_duration(minutes(120)), "2 horas");
+        assert_eq!(locale("ja").format_duration(minutes(90)), "1時間30分");
+        assert_eq!(locale("en").plural(1.0, locale("en").servings), "1 serving");
+        assert_eq!(
+            locale("fr").plural(1.5, locale("fr").servings),
+            "1,5 portion"
+        );
+        assert_eq!(
+            locale("de").plural(4.0, locale("de").servings),
+            "4 Portionen"
+        );
+    }
+}
@@ -1,13 +1,26 @@
 use console::style;
-use recipe_parser::Token;
+use recipe_parser::{ParseOptions, SpecVersion, TemperatureScale, Token, parse_with, unescape};
 use std::{fs, path::PathBuf};
 
 use clap::{Parser, Subcommand};
 use inflector::Inflector;
+use recp::Recipe;
+use recp::cost::{PriceList, cost};
+use recp::diet::{Attributes, DietReport, analyze};
+use recp::diff::{ChangeKind, ListChange, RecipeDiff, diff};
+use recp::index::{INDEX_FILE, Index, Query, recipe_files};
+use recp::locale::Locale;
+use recp::merge::merge;
+use recp::names::Normalizer;
+use recp::nutrition::{NutrientTable, Nutrients, nutrition};
+use recp::pantry::{Pantry, suggest};
+use recp::recipe::{Ingredient, RecipeRef, Temperature};
+use recp::translate::{Catalog, TranslationIssue, apply, check, extract, stale};
+use recp::units::{format_duration, format_quantity, parse_duration};
+use std::collections::BTreeMap;
 use std::io::Write;
+use std::time::Duration;
 use tabwriter::TabWriter;
-mod recipe;
-use recipe::Recipe;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
@@ -19,92 +32,936 @@ struct Cli {
 #[derive(Debug, Subcommand)]
 enum Commands {
     #[command(arg_required_else_help = true, visible_alias = "s")]
-    Show { recipes: Vec<PathBuf> },
+    Show {
+        recipes: Vec<PathBuf>,
+        /// File mapping ingredients to allergens and diet attributes, like `butter: dairy`
+        #[arg(long)]
+        attributes: Option<PathBuf>,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Show the temperatures in another scale: C, F, K or gas mark
+        #[arg(long)]
+        temp: Option<TemperatureScale>,
+        /// Print the recipes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare two versions of a recipe: ingredients, metadata, timers, steps and backstory
+    #[command(arg_required_else_help = true)]
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Print the changes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Three way merge of recipes, to be used as a git merge driver.
+    ///
+    /// The result is written to `ours`, and the exit code is 1 when there are conflicts.
+    #[command(arg_required_else_help = true)]
+    MergeDriver {
+        /// The common ancestor, `%O` in git
+        base: PathBuf,
+        /// Our version, `%A` in git
+        ours: PathBuf,
+        /// Their version, `%B` in git
+        theirs: PathBuf,
+    },
+
+    /// Translate recipes with gettext PO files
+    #[command(subcommand)]
+    Translate(TranslateCommand),
+
+    /// Check recipes for invalid metadata, constructs their spec version lacks and contradicted diet tags
+    #[command(arg_required_else_help = true)]
+    Lint {
+        recipes: Vec<PathBuf>,
+        /// File mapping ingredients to allergens and diet attributes, like `butter: dairy`
+        #[arg(long)]
+        attributes: Option<PathBuf>,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Report constructs this version of the spec doesn't support, like 0.2,
+        /// instead of the version the recipes declare
+        #[arg(long)]
+        spec_version: Option<SpecVersion>,
+    },
+
+    /// Parse all the recipes in a directory into an index file
+    #[command(arg_required_else_help = true)]
+    Index {
+        dir: PathBuf,
+        /// Where to write the index
+        #[arg(short, long, default_value = INDEX_FILE)]
+        output: PathBuf,
+    },
+
+    /// Search the recipes of an index
+    Search {
+        /// Index written by `recp index`
+        #[arg(short, long, default_value = INDEX_FILE)]
+        index: PathBuf,
+        /// Recipes using the ingredient, can be repeated
+        #[arg(long = "ingredient")]
+        ingredients: Vec<String>,
+        /// Recipes not using the ingredient, can be repeated
+        #[arg(long = "without-ingredient")]
+        without_ingredients: Vec<String>,
+        /// Recipes with the tag, can be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Recipes using the material, can be repeated
+        #[arg(long = "material")]
+        materials: Vec<String>,
+        /// Recipes not using the material, can be repeated
+        #[arg(long = "without-material")]
+        without_materials: Vec<String>,
+        /// Maximum total time, like `30 minutes`. Recipes without times in the
+        /// metadata use the sum of their timers
+        #[arg(long, value_parser = parse_duration_arg)]
+        max_time: Option<Duration>,
+        /// Language of the recipe, like `en` or `en-US`
+        #[arg(long)]
+        lang: Option<String>,
+        /// Words found in the name or instructions
+        text: Option<String>,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Rank the recipes of a directory by the ingredients we already have
+    #[command(arg_required_else_help = true)]
+    Suggest {
+        dir: PathBuf,
+        /// File with one ingredient per line, like `tofu: 400 gr`
+        #[arg(short, long)]
+        pantry: PathBuf,
+        /// Show only the best recipes
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Calories and macronutrients of a recipe, from a nutrient table
+    #[command(arg_required_else_help = true)]
+    Nutrition {
+        recipe: PathBuf,
+        /// CSV or JSON with the nutrients per 100 g of each food
+        #[arg(short, long)]
+        table: PathBuf,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Estimate the cost of recipes from a price list
+    #[command(arg_required_else_help = true)]
+    Cost {
+        recipes: Vec<PathBuf>,
+        /// CSV with the price of each ingredient
+        #[arg(short, long)]
+        prices: PathBuf,
+        /// File with ingredient synonyms, like `chickpeas = garbanzo`
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        /// Print the reports as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TranslateCommand {
+    /// Write the texts to translate of a recipe as a PO file
+    #[command(arg_required_else_help = true)]
+    Extract {
+        recipe: PathBuf,
+        /// Language of the translations, like `es`
+        #[arg(long)]
+        lang: Option<String>,
+        /// Where to write the PO file, instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write the recipe with the translations of a PO file
+    #[command(arg_required_else_help = true)]
+    Apply {
+        recipe: PathBuf,
+        translations: PathBuf,
+        /// Where to write the translated recipe, instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Report the translations that break the ingredients, timers or materials of the recipe,
+    /// and the ones made for texts that changed
+    #[command(arg_required_else_help = true)]
+    Check {
+        recipe: PathBuf,
+        translations: PathBuf,
+    },
+}
+
+fn load_catalog(path: &PathBuf) -> Catalog {
+    let content = fs::read_to_string(path).expect("Could not read the given file");
+    Catalog::parse_po(&content).unwrap_or_else(|error| {
+        eprintln!("Failed to read the translations:\n\n{}", error);
+        std::process::exit(1);
+    })
+}
+
+fn write_output(output: Option<PathBuf>, content: &str) {
+    match output {
+        Some(path) => fs::write(path, content).expect("Could not write the output file"),
+        None => print!("{}", content),
+    }
+}
+
+fn format_money(amounts: &BTreeMap<String, f64>) -> String {
+    amounts
+        .iter()
+        .map(|(currency, amount)| format!("{amount:.2} {currency}"))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+fn load_normalizer(synonyms: Option<PathBuf>) -> Normalizer {
+    let Some(path) = synonyms else {
+        return Normalizer::default();
+    };
+    Normalizer::load(&path).unwrap_or_else(|error| {
+        eprintln!("Failed to read the synonyms:\n\n{}", error);
+        std::process::exit(1);
+    })
+}
+
+fn load_attributes(attributes: Option<PathBuf>) -> Attributes {
+    let Some(path) = attributes else {
+        return Attributes::default();
+    };
+    Attributes::load(&path).unwrap_or_else(|error| {
+        eprintln!("Failed to read the attributes:\n\n{}", error);
+        std::process::exit(1);
+    })
+}
+
+fn parse_duration_arg(value: &str) -> Result<Duration, String> {
+    parse_duration(value).ok_or_else(|| format!("{value:?} is not a duration, like 30 minutes"))
+}
+
+fn change_marker(kind: ChangeKind) -> String {
+    match kind {
+        ChangeKind::Added => style("+").green().bold().to_string(),
+        ChangeKind::Removed => style("-").red().bold().to_string(),
+        ChangeKind::Changed => style("~").yellow().bold().to_string(),
+    }
+}
+
+fn format_change(old: Option<&str>, new: Option<&str>) -> String {
+    match (old, new) {
+        (Some(old), Some(new)) => format!("{} -> {}", style(old).red(), style(new).green()),
+        (Some(old), None) => style(old).red().to_string(),
+        (None, new) => style(new.unwrap_or_default()).green().to_string(),
+    }
+}
+
+fn print_list_changes(title: &str, label: &str, changes: &[ListChange]) {
+    if changes.is_empty() {
+        return;
+    }
+    println!("{}\n", style(title).underlined().bold());
+    for change in changes {
+        let position = change
+            .new_position
+            .or(change.old_position)
+            .unwrap_or_default();
+        println!(
+            "  {} {label} {position}: {}",
+            change_marker(change.kind),
+            format_change(change.old.as_deref(), change.new.as_deref())
+        );
+    }
+    println!();
+}
+
+fn print_diff(diff: &RecipeDiff) {
+    if !diff.metadata.is_empty() {
+        println!("{}\n", style("Metadata").underlined().bold());
+        for change in &diff.metadata {
+            println!(
+                "  {} {}: {}",
+                change_marker(change.kind),
+                change.key,
+                format_change(change.old.as_deref(), change.new.as_deref())
+            );
+        }
+        println!();
+    }
+    if !diff.ingredients.is_empty() {
+        println!("{}\n", style("Ingredients").underlined().bold());
+        for change in &diff.ingredients {
+            let delta = change
+                .delta
+                .as_ref()
+                .map(|delta| format!(" ({delta})"))
+                .unwrap_or_default();
+            println!(
+                "  {} {} {}{}",
+                change_marker(change.kind),
+                style(&change.name).cyan().bold(),
+                format_change(change.old.as_deref(), change.new.as_deref()),
+                style(delta).dim()
+            );
+        }
+        println!();
+    }
+    print_list_changes("Timers", "timer", &diff.timers);
+    print_list_changes("Steps", "step", &diff.steps);
+    if let Some(backstory) = &diff.backstory {
+        println!("{}\n", style("Backstory").underlined().bold());
+        if let Some(old) = &backstory.old {
+            println!("{}", style(old).red());
+        }
+        if let Some(new) = &backstory.new {
+            println!("{}", style(new).green());
+        }
+    }
+}
+
+fn print_ingredient(ing: &Ingredient) {
+    let mut amount = ing
+        .amounts()
+        .map(|amount| {
+            format!(
+                "{} {}",
+                amount.quantity.unwrap_or_default(),
+                amount.unit.unwrap_or_default()
+            )
+            .trim()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(" or ");
+    let details: Vec<String> = ing
+        .note
+        .clone()
+        .into_iter()
+        .chain(ing.optional.then(|| "optional".to_string()))
+        .collect();
+    if !details.is_empty() {
+        amount = format!(
+            "{}  {}",
+            amount.trim(),
+            style(details.join(", ")).italic().dim()
+        );
+    }
+    let mut tw = TabWriter::new(vec![]).minwidth(32).padding(10);
+    write!(&mut tw, "  {}\t{}", style(&ing.name).cyan().bold(), amount).unwrap();
+    tw.flush().unwrap();
+    let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
+    println!("{}", written);
+}
+
+fn print_recipe_ref(ing: &RecipeRef) {
+    let amount = format!(
+        "{} {}",
+        ing.quantity.as_deref().unwrap_or_default(),
+        ing.unit.as_deref().unwrap_or_default()
+    );
+    let mut tw = TabWriter::new(vec![]).minwidth(32).padding(10);
+    write!(
+        &mut tw,
+        "  {}\t{}",
+        style(&ing.name).magenta().bold(),
+        amount
+    )
+    .unwrap();
+    tw.flush().unwrap();
+    let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
+    println!("{}", written);
 }
 
 fn main() {
     let args = Cli::parse();
 
     match args.command {
-        Commands::Show { recipes } => {
+        Commands::Show {
+            recipes,
+            attributes,
+            synonyms,
+            temp,
+            json,
+        } => {
+            let normalizer = load_normalizer(synonyms);
+            let with_diet = attributes.is_some();
+            let attributes = load_attributes(attributes);
+            let mut exports = Vec::new();
             for recipe_path in recipes {
                 let content =
-                    fs::read_to_string(recipe_path).expect("Could not read the given file");
+                    fs::read_to_string(&recipe_path).expect("Could not read the given file");
                 let recipe = Recipe::try_from(content.as_str());
                 if let Err(error) = recipe {
                     eprintln!("Failed to parse the recipe file:\n\n{}", error);
                     std::process::exit(1);
                 }
-                let recipe = recipe.unwrap();
-                if let Some(name) = recipe.name {
+                let mut recipe = recipe.unwrap();
+                if let Some(scale) = temp {
+                    recipe.convert_temperatures(scale);
+                }
+                let diet = with_diet
+                    .then(|| analyze(&recipe, Some(&recipe_path), &attributes, &normalizer));
+                if json {
+                    exports.push(serde_json::json!({
+                        "path": recipe_path,
+                        "recipe": recipe,
+                        "diet": diet,
+                    }));
+                    continue;
+                }
+                let locale = Locale::get(recipe.metadata.lang());
+                if let Some(name) = &recipe.name {
                     println!("{}\n", style(name.to_title_case()).bold().blue());
                 }
-                if recipe.ingredients.len() > 0 || recipe.recipes_refs.len() > 0 {
-                    println!("{}\n", style("Ingredients").underlined());
+                let summary: Vec<String> = recipe
+                    .metadata
+                    .servings()
+                    .map(|servings| locale.plural(servings as f64, locale.servings))
+                    .into_iter()
+                    .chain(
+                        recipe
+                            .metadata
+                            .total_time()
+                            .map(|time| locale.format_duration(time)),
+                    )
+                    .collect();
+                if !summary.is_empty() {
+                    println!("{}\n", style(summary.join(" · ")).dim());
                 }
-                if recipe.ingredients.len() > 0 {
-                    for ing in recipe.ingredients {
-                        let amount = format!(
-                            "{} {}",
-                            ing.quantity.unwrap_or_default(),
-                            ing.unit.unwrap_or_default()
-                        );
-                        let mut tw = TabWriter::new(vec![]).minwidth(32).padding(10);
-                        write!(&mut tw, "  {}\t{}", style(ing.name).cyan().bold(), amount).unwrap();
-                        tw.flush().unwrap();
-                        let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
-                        println!("{}", written);
-                        // println!("{} {} ", style(ing.name).bold(), amount.trim())
-                    }
+                if !recipe.ingredients.is_empty() || !recipe.recipes_refs.is_empty() {
+                    println!("{}\n", style(locale.ingredients).underlined());
                 }
-                if recipe.recipes_refs.len() > 0 {
-                    for ing in recipe.recipes_refs {
-                        let amount = format!(
-                            "{} {}",
-                            ing.quantity.unwrap_or_default(),
-                            ing.unit.unwrap_or_default()
-                        );
-                        let mut tw = TabWriter::new(vec![]).minwidth(32).padding(10);
-                        write!(
-                            &mut tw,
-                            "  {}\t{}",
-                            style(ing.name).magenta().bold(),
-                            amount
-                        )
-                        .unwrap();
-                        tw.flush().unwrap();
-                        let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
-                        println!("{}", written);
-                        // println!("{} {} ", style(ing.name).bold(), amount.trim())
+                let mut listed_any = false;
+                for section in &recipe.sections {
+                    if section.ingredients.is_empty() && section.recipes_refs.is_empty() {
+                        continue;
+                    }
+                    if let Some(name) = &section.name {
+                        if listed_any {
+                            println!();
+                        }
+                        println!("  {}", style(name).bold());
+                    }
+                    listed_any = true;
+                    for ing in &section.ingredients {
+                        print_ingredient(ing);
+                    }
+                    for ing in &section.recipes_refs {
+                        print_recipe_ref(ing);
                     }
                 }
-                println!("\n\n{}\n", style("Instructions").underlined().bold());
+                println!("\n\n{}\n", style(locale.instructions).underlined().bold());
                 let instru: String = recipe
                     .instructions
                     .iter()
                     .filter_map(|instruct| match instruct {
                         Token::Metadata { key: _, value: _ } => None,
-                        Token::Ingredient {
-                            name,
-                            quantity: _,
-                            unit: _,
-                        } => Some(format!("{}", style(name).cyan().bold())),
+                        Token::Ingredient { name, .. } => {
+                            Some(format!("{}", style(unescape(name)).cyan().bold()))
+                        }
                         Token::RecipeRef {
                             name,
                             quantity: _,
                             unit: _,
-                        } => Some(format!("{}", style(name).magenta())),
-                        Token::Timer(v) => Some(format!("{}", style(v).red().bold())),
-                        Token::Material(v) => Some(format!("{}", style(v).yellow())),
-                        Token::Word(v) | Token::Space(v) => Some(format!("{v}")),
+                        } => Some(format!("{}", style(unescape(name)).magenta())),
+                        Token::Timer(v) => Some(format!("{}", style(unescape(v)).red().bold())),
+                        Token::Material(v) => Some(format!("{}", style(unescape(v)).yellow())),
+                        Token::Tag { body, .. } => {
+                            Some(format!("{}", style(unescape(body)).blue()))
+                        }
+                        Token::Temperature { value, scale } => {
+                            let temperature = Temperature {
+                                value: value.to_string(),
+                                scale: *scale,
+                            };
+                            let temperature = temp
+                                .and_then(|scale| temperature.convert_to(scale))
+                                .unwrap_or(temperature);
+                            Some(format!("{}", style(temperature).red()))
+                        }
+                        Token::Word(v) => Some(unescape(v).into_owned()),
+                        Token::Section(v) => Some(format!("{}", style(unescape(v)).underlined())),
+                        Token::Space(v) => Some(v.to_string()),
 
                         Token::Comment(_) => None,
                         Token::Backstory(_) => None,
+                        Token::FrontMatter { .. } => None,
                     })
                     .collect();
 
-                println!("{}", instru.trim())
+                println!("{}", instru.trim());
                 // println!("{}", recipe.instructions);
+                if let Some(diet) = diet {
+                    println!("\n\n{}\n", style(locale.diet).underlined().bold());
+                    println!("  {}: {}", locale.allergens, diet.allergens.join(", "));
+                    println!("  {}: {}", locale.suitable_for, diet.diets.join(", "));
+                    // the diets don't take the unmapped ingredients into account
+                    if !diet.unknown.is_empty() {
+                        println!(
+                            "  {}: {}",
+                            style(locale.unchecked).yellow(),
+                            diet.unknown.join(", ")
+                        );
+                    }
+                    for contradiction in &diet.contradictions {
+                        println!(
+                            "  {} {}",
+                            style("warning").yellow(),
+                            DietReport::describe(contradiction)
+                        );
+                    }
+                }
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&exports).unwrap());
+            }
+        }
+        Commands::Diff {
+            old,
+            new,
+            synonyms,
+            json,
+        } => {
+            let normalizer = load_normalizer(synonyms);
+            let old = fs::read_to_string(old).expect("Could not read the given file");
+            let new = fs::read_to_string(new).expect("Could not read the given file");
+            let parse = |content| {
+                Recipe::try_from(content).unwrap_or_else(|error| {
+                    eprintln!("Failed to parse the recipe file:\n\n{}", error);
+                    std::process::exit(1);
+                })
+            };
+            let diff = diff(&parse(old.as_str()), &parse(new.as_str()), &normalizer);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+            } else if diff.is_empty() {
+                println!("No changes");
+            } else {
+                print_diff(&diff);
+            }
+        }
+        Commands::MergeDriver { base, ours, theirs } => {
+            let read =
+                |path: &PathBuf| fs::read_to_string(path).expect("Could not read the given file");
+            let merged =
+                merge(&read(&base), &read(&ours), &read(&theirs)).unwrap_or_else(|error| {
+                    eprintln!("Failed to parse the recipe file:\n\n{}", error);
+                    std::process::exit(1);
+                });
+            fs::write(&ours, merged.text).expect("Could not write the merged recipe");
+            if merged.conflicts > 0 {
+                eprintln!("{} conflicts in {}", merged.conflicts, ours.display());
+                std::process::exit(1);
+            }
+        }
+        Commands::Translate(command) => {
+            let report = |issues: Vec<TranslationIssue>| {
+                for issue in issues {
+                    eprintln!("{} {}", style("error").red(), issue);
+                }
+                std::process::exit(1);
+            };
+            match command {
+                TranslateCommand::Extract {
+                    recipe,
+                    lang,
+                    output,
+                } => {
+                    let source = fs::read_to_string(recipe).expect("Could not read the given file");
+                    let mut catalog = extract(&source).unwrap_or_else(|error| {
+                        eprintln!("Failed to parse the recipe file:\n\n{}", error);
+                        std::process::exit(1);
+                    });
+                    catalog.lang = lang;
+                    write_output(output, &catalog.to_po());
+                }
+                TranslateCommand::Apply {
+                    recipe,
+                    translations,
+                    output,
+                } => {
+                    let source = fs::read_to_string(recipe).expect("Could not read the given file");
+                    let catalog = load_catalog(&translations);
+                    match apply(&source, &catalog) {
+                        Ok(translated) => {
+                            for issue in stale(&source, &catalog) {
+                                eprintln!("{} {}", style("warning").yellow(), issue);
+                            }
+                            write_output(output, &translated)
+                        }
+                        Err(issues) => report(issues),
+                    }
+                }
+                TranslateCommand::Check {
+                    recipe,
+                    translations,
+                } => {
+                    let source = fs::read_to_string(recipe).expect("Could not read the given file");
+                    let issues = check(&source, &load_catalog(&translations));
+                    if !issues.is_empty() {
+                        report(issues);
+                    }
+                }
+            }
+        }
+        Commands::Lint {
+            recipes,
+            attributes,
+            synonyms,
+            spec_version,
+        } => {
+            if let Some(version) = spec_version.filter(|version| !version.is_supported()) {
+                eprintln!("Failed to lint the recipes:\n\nunknown spec version {version}");
+                std::process::exit(1);
+            }
+            let options = ParseOptions {
+                version: spec_version,
+                ..Default::default()
+            };
+            let normalizer = load_normalizer(synonyms);
+            let attributes = load_attributes(attributes);
+            let mut problems = 0;
+            for path in recipes {
+                let mut report = |message: String| {
+                    problems += 1;
+                    println!("{}: {}", path.display(), message);
+                };
+                let content = match fs::read_to_string(&path) {
+                    Ok(content) => content,
+                    Err(error) => {
+                        report(format!("could not read the file: {error}"));
+                        continue;
+                    }
+                };
+                let recipe = match Recipe::try_from(content.as_str()) {
+                    Ok(recipe) => recipe,
+                    Err(error) => {
+                        report(format!("could not parse the recipe: {error}"));
+                        continue;
+                    }
+                };
+                for issue in recipe.metadata.validate() {
+                    report(issue.to_string());
+                }
+                // An unknown declared version is reported by the metadata validation
+                if let Ok(parsed) = parse_with(&content, &options) {
+                    for warning in parsed.warnings {
+                        let line = content[..warning.span.start].matches('\n').count() + 1;
+                        report(format!("line {line}: {warning}"));
+                    }
+                }
+                let diet = analyze(&recipe, Some(&path), &attributes, &normalizer);
+                for contradiction in &diet.contradictions {
+                    report(DietReport::describe(contradiction));
+                }
+            }
+            if problems > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Index { dir, output } => {
+            let (index, errors) = Index::build(&dir);
+            for error in &errors {
+                eprintln!(
+                    "{} {}: {}",
+                    style("skipped").yellow(),
+                    error.path.display(),
+                    error.reason
+                );
+            }
+            if let Err(error) = index.save(&output) {
+                eprintln!("Failed to write the index:\n\n{}", error);
+                std::process::exit(1);
+            }
+            println!(
+                "Indexed {} recipes into {}",
+                index.entries.len(),
+                output.display()
+            );
+        }
+        Commands::Search {
+            index,
+            ingredients,
+            without_ingredients,
+            tags,
+            materials,
+            without_materials,
+            max_time,
+            lang,
+            text,
+            synonyms,
+            json,
+        } => {
+            let normalizer = load_normalizer(synonyms);
+            let index = Index::load(&index).unwrap_or_else(|error| {
+                eprintln!(
+                    "Failed to load the index, run `recp index` first:\n\n{}",
+                    error
+                );
+                std::process::exit(1);
+            });
+            let query = Query {
+                ingredients,
+                without_ingredients,
+                tags,
+                materials,
+                without_materials,
+                max_time,
+                lang,
+                text,
+            };
+            let results = index.search(&query, &normalizer);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&results).unwrap());
+                return;
+            }
+            let mut tw = TabWriter::new(vec![]).padding(4);
+            writeln!(&mut tw, "Name\tTime\tTags\tPath").unwrap();
+            for entry in results {
+                writeln!(
+                    &mut tw,
+                    "{}\t{}\t{}\t{}",
+                    entry.title(),
+                    entry.total_time().map(format_duration).unwrap_or_default(),
+                    entry.tags.join(", "),
+                    entry.path.display()
+                )
+                .unwrap();
+            }
+            tw.flush().unwrap();
+            print!("{}", String::from_utf8(tw.into_inner().unwrap()).unwrap());
+        }
+        Commands::Suggest {
+            dir,
+            pantry,
+            limit,
+            synonyms,
+            json,
+        } => {
+            let normalizer = load_normalizer(synonyms);
+            let pantry = Pantry::load(&pantry).unwrap_or_else(|error| {
+                eprintln!("Failed to read the pantry:\n\n{}", error);
+                std::process::exit(1);
+            });
+            let mut contents = Vec::new();
+            for path in recipe_files(&dir) {
+                match fs::read_to_string(&path) {
+                    Ok(content) => contents.push((path, content)),
+                    Err(error) => eprintln!(
+                        "{} {}: {}",
+                        style("skipped").yellow(),
+                        path.display(),
+                        error
+                    ),
+                }
+            }
+            let mut recipes = Vec::new();
+            for (path, content) in &contents {
+                match Recipe::try_from(content.as_str()) {
+                    Ok(recipe) => recipes.push((path.clone(), recipe)),
+                    Err(error) => eprintln!(
+                        "{} {}: {}",
+                        style("skipped").yellow(),
+                        path.display(),
+                        error
+                    ),
+                }
+            }
+            let mut suggestions = suggest(
+                &pantry,
+                &normalizer,
+                recipes.iter().map(|(path, recipe)| (path.clone(), recipe)),
+            );
+            suggestions.truncate(limit.unwrap_or(suggestions.len()));
+            if json {
+                println!("{}", serde_json::to_string_pretty(&suggestions).unwrap());
+                return;
+            }
+            for suggestion in suggestions {
+                let have = suggestion.available.len();
+                let total = have + suggestion.missing.len();
+                let title = suggestion
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| suggestion.path.display().to_string());
+                println!(
+                    "{}  {}",
+                    style(title.to_title_case()).bold().blue(),
+                    style(format!("{have}/{total}")).dim()
+                );
+                for missing in &suggestion.missing {
+                    match &missing.amount {
+                        Some(amount) => println!("  missing {} ({})", missing.name, amount),
+                        None => println!("  missing {}", missing.name),
+                    }
+                }
+            }
+        }
+        Commands::Nutrition {
+            recipe,
+            table,
+            synonyms,
+            json,
+        } => {
+            let normalizer = load_normalizer(synonyms);
+            let table = NutrientTable::load(&table).unwrap_or_else(|error| {
+                eprintln!("Failed to read the nutrient table:\n\n{}", error);
+                std::process::exit(1);
+            });
+            let content = fs::read_to_string(recipe).expect("Could not read the given file");
+            let recipe = Recipe::try_from(content.as_str()).unwrap_or_else(|error| {
+                eprintln!("Failed to parse the recipe file:\n\n{}", error);
+                std::process::exit(1);
+            });
+            let report = nutrition(&recipe, &table, &normalizer);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+                return;
+            }
+            let mut tw = TabWriter::new(vec![]).padding(4);
+            writeln!(&mut tw, "Ingredient\tGrams\tKcal\tProtein\tFat\tCarbs").unwrap();
+            let row = |tw: &mut TabWriter<Vec<u8>>, name: &str, grams: &str, n: &Nutrients| {
+                writeln!(
+                    tw,
+                    "{}\t{}\t{:.0}\t{:.1}\t{:.1}\t{:.1}",
+                    name, grams, n.calories, n.protein, n.fat, n.carbs
+                )
+                .unwrap();
+            };
+            for ingredient in &report.ingredients {
+                let grams = format_quantity(ingredient.grams);
+                row(&mut tw, &ingredient.name, &grams, &ingredient.nutrients);
+            }
+            row(&mut tw, "Total", "", &report.total);
+            if let (Some(servings), Some(per_serving)) = (report.servings, &report.per_serving) {
+                row(
+                    &mut tw,
+                    &format!("Per serving ({servings})"),
+                    "",
+                    per_serving,
+                );
+            }
+            tw.flush().unwrap();
+            print!("{}", String::from_utf8(tw.into_inner().unwrap()).unwrap());
+            for unresolved in report.unmatched.iter().chain(&report.unconvertible) {
+                eprintln!(
+                    "{} {} {}",
+                    style("skipped").yellow(),
+                    unresolved.name,
+                    unresolved.reason
+                );
+            }
+        }
+        Commands::Cost {
+            recipes,
+            prices,
+            synonyms,
+            json,
+        } => {
+            let normalizer = load_normalizer(synonyms);
+            let prices = PriceList::load(&prices).unwrap_or_else(|error| {
+                eprintln!("Failed to read the price list:\n\n{}", error);
+                std::process::exit(1);
+            });
+            let mut reports = Vec::new();
+            for path in recipes {
+                let content = fs::read_to_string(&path).expect("Could not read the given file");
+                let recipe = Recipe::try_from(content.as_str()).unwrap_or_else(|error| {
+                    eprintln!("Failed to parse the recipe file:\n\n{}", error);
+                    std::process::exit(1);
+                });
+                let title = recipe
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| path.display().to_string());
+                let report = cost(&recipe, &path, &prices, &normalizer);
+                reports.push((path, title, report));
+            }
+            if json {
+                let reports: Vec<_> = reports
+                    .iter()
+                    .map(|(path, title, report)| {
+                        serde_json::json!({
+                            "path": path,
+                            "title": title,
+                            "report": report,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+                return;
+            }
+            for (_, title, report) in reports {
+                println!("{}\n", style(title.to_title_case()).bold().blue());
+                let mut tw = TabWriter::new(vec![]).padding(4);
+                for item in &report.items {
+                    let from = item
+                        .recipe
+                        .as_ref()
+                        .map(|recipe| format!(" ({recipe})"))
+                        .unwrap_or_default();
+                    writeln!(
+                        &mut tw,
+                        "  {}{}\t{:.2} {}",
+                        item.name, from, item.cost, item.currency
+                    )
+                    .unwrap();
+                }
+                writeln!(&mut tw, "  Total\t{}", format_money(&report.totals)).unwrap();
+                if let Some(servings) = report.servings {
+                    writeln!(
+                        &mut tw,
+                        "  Per serving ({servings})\t{}",
+                        format_money(&report.per_serving)
+                    )
+                    .unwrap();
+                }
+                tw.flush().unwrap();
+                print!("{}", String::from_utf8(tw.into_inner().unwrap()).unwrap());
+                for unpriced in &report.unpriced {
+                    let from = unpriced
+                        .recipe
+                        .as_ref()
+                        .map(|recipe| format!(" ({recipe})"))
+                        .unwrap_or_default();
+                    println!(
+                        "  {} {}{} {}",
+                        style("unpriced").yellow(),
+                        unpriced.name,
+                        from,
+                        style(&unpriced.reason).dim()
+                    );
+                }
+                println!();
             }
         }
     }
@@ -0,0 +1,414 @@
+use std::ops::Range;
+
+use recipe_parser::{Token, parse_with_spans};
+
+use crate::metadata::normalize_key;
+
+/// The result of merging two versions of a recipe
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub text: String,
+    /// Number of conflicts written with markers, the merge is clean when it's 0
+    pub conflicts: usize,
+}
+
+/// Split a recipe in the blocks that are merged: the front matter, each metadata
+/// line, the paragraphs of the instructions, the backstory, and the whitespace
+/// between them. The blocks are slices of the recipe, together they are the recipe.
+fn blocks(input: &str) -> Result<Vec<&str>, String> {
+    let tokens = parse_with_spans(input).map_err(|error| error.to_string())?;
+    let mut blocks = Vec::new();
+    // the paragraph being read, and the whitespace after it
+    let mut paragraph: Option<Range<usize>> = None;
+    let mut space: Option<Range<usize>> = None;
+    let mut flush = |paragraph: &mut Option<Range<usize>>, space: &mut Option<Range<usize>>| {
+        blocks.extend(paragraph.take().map(|span| &input[span]));
+        blocks.extend(space.take().map(|span| &input[span]));
+    };
+    for (token, span) in tokens {
+        match token {
+            Token::Space(_) => match &mut space {
+                Some(space) => space.end = span.end,
+                None => space = Some(span),
+            },
+            Token::FrontMatter { .. } | Token::Metadata { .. } | Token::Backstory(_) => {
+                flush(&mut paragraph, &mut space);
+                flush(&mut Some(span), &mut None);
+            }
+            _ => {
+                // a blank line ends the paragraph
+                let joined = space
+                    .as_ref()
+                    .is_none_or(|space| input[space.clone()].matches('\n').count() < 2);
+                match &mut paragraph {
+                    Some(paragraph) if joined => {
+                        paragraph.end = span.end;
+                        space = None;
+                    }
+                    _ => {
+                        flush(&mut paragraph, &mut space);
+                        paragraph = Some(span);
+                    }
+                }
+            }
+        }
+    }
+    flush(&mut paragraph, &mut space);
+    Ok(blocks)
+}
+
+/// A region of a merge, either agreed by both sides or in conflict
+#[derive(Debug, Clone, PartialEq)]
+enum Chunk<T> {
+    Clean(Vec<T>),
+    Conflict {
+        base: Vec<T>,
+        ours: Vec<T>,
+        theirs: Vec<T>,
+    },
+}
+
+/// Three way merge of a single value: the side that changed wins
+fn merge_value<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Chunk<T> {
+    if ours == theirs || theirs == base {
+        Chunk::Clean(vec![ours.clone()])
+    } else if ours == base {
+        Chunk::Clean(vec![theirs.clone()])
+    } else {
+        Chunk::Conflict {
+            base: vec![base.clone()],
+            ours: vec![ours.clone()],
+            theirs: vec![theirs.clone()],
+        }
+    }
+}
+
+/// For each item of `base`, the position of the same item in `other`, following
+/// their longest common subsequence
+fn matches<T: PartialEq>(base: &[T], other: &[T]) -> Vec<Option<usize>> {
+    let mut lengths = vec![vec![0usize; other.len() + 1]; base.len() + 1];
+    for i in (0..base.len()).rev() {
+        for j in (0..other.len()).rev() {
+            lengths[i][j] = if base[i] == other[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut matches = vec![None; base.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < base.len() && j < other.len() {
+        if base[i] == other[j] {
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// The part of `base` that `side` changed, and what it was replaced with
+fn edit<'a, T: PartialEq>(base: &[T], side: &'a [T]) -> (Range<usize>, &'a [T]) {
+    let prefix = base
+        .iter()
+        .zip(side)
+        .take_while(|(base, side)| base == side)
+        .count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(side[prefix..].iter().rev())
+        .take_while(|(base, side)| base == side)
+        .count();
+    (
+        prefix..base.len() - suffix,
+        &side[prefix..side.len() - suffix],
+    )
+}
+
+/// Merge a hunk changed by both sides.
+///
+/// Hunks with the same number of items on all sides merge item by item, and the
+/// others merge when the sides changed different items of the hunk.
+fn merge_hunk<T: PartialEq + Clone>(base: &[T], ours: &[T], theirs: &[T]) -> Vec<Chunk<T>> {
+    if let Chunk::Clean(hunk) = merge_value(&base, &ours, &theirs) {
+        return vec![Chunk::Clean(hunk[0].to_vec())];
+    }
+    if base.len() == ours.len() && base.len() == theirs.len() {
+        return base
+            .iter()
+            .zip(ours)
+            .zip(theirs)
+            .map(|((base, ours), theirs)| merge_value(base, ours, theirs))
+            .collect();
+    }
+    let (ours_range, ours_items) = edit(base, ours);
+    let (theirs_range, theirs_items) = edit(base, theirs);
+    let same_insertion = ours_range.is_empty() && theirs_range == ours_range;
+    let disjoint = ours_range.end <= theirs_range.start || theirs_range.end <= ours_range.start;
+    if !disjoint || same_insertion {
+        return vec![Chunk::Conflict {
+            base: base.to_vec(),
+            ours: ours.to_vec(),
+            theirs: theirs.to_vec(),
+        }];
+    }
+    let mut edits = [(ours_range, ours_items), (theirs_range, theirs_items)];
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+    let [(first, first_items), (second, second_items)] = edits;
+    let mut merged = base[..first.start].to_vec();
+    merged.extend_from_slice(first_items);
+    merged.extend_from_slice(&base[first.end..second.start]);
+    merged.extend_from_slice(second_items);
+    merged.extend_from_slice(&base[second.end..]);
+    vec![Chunk::Clean(merged)]
+}
+
+/// Three way merge of two lists (diff3).
+///
+/// The items kept by both sides split the lists in hunks, a hunk changed only
+/// by one side takes that change, and a hunk changed by both sides is a conflict
+/// unless they made the same change or changed different items of it.
+fn merge_lists<T: PartialEq + Clone>(base: &[T], ours: &[T], theirs: &[T]) -> Vec<Chunk<T>> {
+    let in_ours = matches(base, ours);
+    let in_theirs = matches(base, theirs);
+    let mut chunks: Vec<Chunk<T>> = Vec::new();
+    let push_clean = |chunks: &mut Vec<Chunk<T>>, items: &[T]| match chunks.last_mut() {
+        Some(Chunk::Clean(clean)) => clean.extend_from_slice(items),
+        _ if items.is_empty() => {}
+        _ => chunks.push(Chunk::Clean(items.to_vec())),
+    };
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        let stable = (i..base.len()).find_map(|b| Some((b, in_ours[b]?, in_theirs[b]?)));
+        let (b, o, t) = stable.unwrap_or((base.len(), ours.len(), theirs.len()));
+        for chunk in merge_hunk(&base[i..b], &ours[j..o], &theirs[k..t]) {
+            match chunk {
+                Chunk::Clean(items) => push_clean(&mut chunks, &items),
+                conflict => chunks.push(conflict),
+            }
+        }
+        let Some((b, _, _)) = stable else {
+            break;
+        };
+        push_clean(&mut chunks, &base[b..=b]);
+        (i, j, k) = (b + 1, o + 1, t + 1);
+    }
+    chunks
+}
+
+/// The tokens of a step, as written
+fn step_tokens(step: &str) -> Option<Vec<&str>> {
+    let tokens = parse_with_spans(step).ok()?;
+    Some(tokens.into_iter().map(|(_, span)| &step[span]).collect())
+}
+
+/// Merge a step changed by both sides, token by token: an ingredient whose amount
+/// changed on one side and a word changed on the other don't conflict
+fn merge_step(base: &str, ours: &str, theirs: &str) -> Option<String> {
+    let chunks = merge_lists(
+        &step_tokens(base)?,
+        &step_tokens(ours)?,
+        &step_tokens(theirs)?,
+    );
+    let mut merged = String::new();
+    for chunk in chunks {
+        match chunk {
+            Chunk::Clean(tokens) => merged.extend(tokens),
+            Chunk::Conflict { .. } => return None,
+        }
+    }
+    Some(merged)
+}
+
+/// Both sides between conflict markers
+fn conflict(ours: &str, theirs: &str) -> String {
+    format!("<<<<<<< ours\n{ours}\n=======\n{theirs}\n>>>>>>> theirs")
+}
+
+/// The metadata lines by normalized key, `None` when there is something else
+fn metadata_lines(text: &str) -> Option<Vec<(String, &str)>> {
+    let mut lines = Vec::new();
+    for (token, span) in parse_with_spans(text).ok()? {
+        match token {
+            Token::Metadata { key, .. } => lines.push((normalize_key(key), &text[span])),
+            Token::Space(_) => {}
+            _ => return None,
+        }
+    }
+    Some(lines)
+}
+
+/// Merge metadata lines changed by both sides by key, keeping the order of our
+/// side and then the keys added by theirs
+fn merge_metadata(base: &str, ours: &str, theirs: &str, conflicts: &mut usize) -> Option<String> {
+    let base = metadata_lines(base)?;
+    let ours = metadata_lines(ours)?;
+    let theirs = metadata_lines(theirs)?;
+    let mut keys: Vec<&str> = Vec::new();
+    for (key, _) in ours.iter().chain(&theirs) {
+        if !keys.contains(&key.as_str()) {
+            keys.push(key);
+        }
+    }
+    let lines_of = |lines: &[(String, &'_ str)], key: &str| -> Vec<String> {
+        lines
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, line)| line.to_string())
+            .collect()
+    };
+    let mut lines = Vec::new();
+    for key in keys {
+        let merged = merge_value(
+            &lines_of(&base, key),
+            &lines_of(&ours, key),
+            &lines_of(&theirs, key),
+        );
+        match merged {
+            Chunk::Clean(values) => lines.extend(values[0].iter().cloned()),
+            Chunk::Conflict { ours, theirs, .. } => {
+                *conflicts += 1;
+                lines.push(conflict(&ours[0].join("\n"), &theirs[0].join("\n")));
+            }
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+/// Merge blocks changed by both sides: metadata merges by key and a step token by
+/// token, the rest conflicts. The whitespace around our side is kept.
+fn merge_changed(base: &str, ours: &str, theirs: &str, conflicts: &mut usize) -> String {
+    let lead = &ours[..ours.len() - ours.trim_start().len()];
+    let tail = &ours[lead.len().max(ours.trim_end().len())..];
+    let (base, ours, theirs) = (base.trim(), ours.trim(), theirs.trim());
+    let single = |text: &str| blocks(text).is_ok_and(|blocks| blocks.len() == 1);
+    let merged = merge_metadata(base, ours, theirs, conflicts)
+        .or_else(|| {
+            (single(base) && single(ours) && single(theirs))
+                .then(|| merge_step(base, ours, theirs))
+                .flatten()
+        })
+        .unwrap_or_else(|| {
+            *conflicts += 1;
+            conflict(ours, theirs)
+        });
+    format!("{lead}{merged}{tail}")
+}
+
+/// Three way merge of recipes, `base` is their common ancestor.
+///
+/// The recipes merge by blocks: the front matter, each metadata line, the steps,
+/// separated by blank lines, and the backstory. The blocks changed by one side
+/// take that change, and the rest are kept as they are written. Metadata changed
+/// on both sides merges by key, and a step changed on both sides merges token by
+/// token, so the ingredients merge by their name.
+/// Only the parts changed differently on both sides conflict, and are written
+/// between `<<<<<<< ours` and `>>>>>>> theirs` markers.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Result<Merge, String> {
+    let base = blocks(base)?;
+    let ours = blocks(ours)?;
+    let theirs = blocks(theirs)?;
+    let mut conflicts = 0;
+    let mut text = String::new();
+    for chunk in merge_lists(&base, &ours, &theirs) {
+        match chunk {
+            Chunk::Clean(blocks) => text.extend(blocks),
+            Chunk::Conflict { base, ours, theirs } => text.push_str(&merge_changed(
+                &base.concat(),
+                &ours.concat(),
+                &theirs.concat(),
+                &mut conflicts,
+            )),
+        }
+    }
+    if conflicts > 0 && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(Merge { text, conflicts })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_lists() {
+        let list = |items: &str| -> Vec<char> { items.chars().collect() };
+        assert_eq!(
+            merge_lists(&list("abc"), &list("xabc"), &list("abcy")),
+            vec![Chunk::Clean(list("xabcy"))]
+        );
+        assert_eq!(
+            merge_lists(&list("abc"), &list("axc"), &list("abc")),
+            vec![Chunk::Clean(list("axc"))]
+        );
+        assert_eq!(
+            merge_lists(&list("abc"), &list("axc"), &list("ayc")),
+            vec![
+                Chunk::Clean(list("a")),
+                Chunk::Conflict {
+                    base: list("b"),
+                    ours: list("x"),
+                    theirs: list("y")
+                },
+                Chunk::Clean(list("c")),
+            ]
+        );
+        // different items next to each other
+        assert_eq!(
+            merge_lists(&list("abc"), &list("axc"), &list("aby")),
+            vec![Chunk::Clean(list("axy"))]
+        );
+        // an insertion next to a changed item
+        assert_eq!(
+            merge_lists(&list("a b"), &list("a c"), &list("a x b")),
+            vec![Chunk::Clean(list("a x c"))]
+        );
+        assert_eq!(
+            merge_lists(&list("ab"), &list("axb"), &list("ayb")),
+            vec![
+                Chunk::Clean(list("a")),
+                Chunk::Conflict {
+                    base: list(""),
+                    ours: list("x"),
+                    theirs: list("y")
+                },
+                Chunk::Clean(list("b")),
+            ]
+        );
+    }
+
+    /// Each fixture is a directory with `base.recp`, `ours.recp`, `theirs.recp`
+    /// and the `merged.recp` expected
+    #[test]
+    fn test_merge_fixtures() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/merge");
+        let mut dirs: Vec<_> = fs::read_dir(&fixtures)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        dirs.sort();
+        assert!(!dirs.is_empty());
+        for dir in dirs {
+            let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+            assert_eq!(
+                blocks(&read("base.recp")).unwrap().concat(),
+                read("base.recp")
+            );
+            let merged =
+                merge(&read("base.recp"), &read("ours.recp"), &read("theirs.recp")).unwrap();
+            assert_eq!(merged.text, read("merged.recp"), "{}", dir.display());
+            let expected_conflicts = read("merged.recp").matches("<<<<<<< ours").count();
+            assert_eq!(merged.conflicts, expected_conflicts, "{}", dir.display());
+        }
+    }
+}
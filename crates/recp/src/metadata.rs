@@ -0,0 +1,366 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use recipe_parser::SpecVersion;
+use serde::Serialize;
+
+use crate::recipe::Amount;
+use crate::units::{parse_duration, parse_quantity};
+
+/// How hard is it to prepare a recipe
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+/// A metadata value that couldn't be understood
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MetadataIssue {
+    pub key: String,
+    pub value: String,
+    pub reason: &'static str,
+}
+
+impl Display for MetadataIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:?} {}", self.key, self.value, self.reason)
+    }
+}
+
+/// Keys are compared ignoring case, and `_` or spaces are the same as `-`.
+///
+/// `Prep Time`, `prep_time` and `prep-time` are the same key.
+pub(crate) fn normalize_key(key: &str) -> String {
+    key.trim().to_lowercase().replace(['_', ' '], "-")
+}
+
+fn parse_servings(value: &str) -> Option<u32> {
+    value.trim().parse().ok().filter(|servings| *servings > 0)
+}
+
+fn parse_tags(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Syntax check of a BCP 47 language tag, like `en`, `en-US` or `zh-Hant-TW`
+fn is_language_tag(value: &str) -> bool {
+    let mut subtags = value.split('-');
+    let language = subtags.next().unwrap_or_default();
+    let valid_language = matches!(language.len(), 2..=3 | 5..=8)
+        && language.chars().all(|c| c.is_ascii_alphabetic());
+    valid_language
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+fn is_url(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    matches!(scheme, "http" | "https") && !host.is_empty() && !value.contains(char::is_whitespace)
+}
+
+/// An amount written as a quantity and an optional unit, like `500 ml`
+pub(crate) fn parse_amount(value: &str) -> Option<Amount> {
+    let value = value.trim();
+    let (quantity, unit) = value.split_once(' ').unwrap_or((value, ""));
+    parse_quantity(quantity)?;
+    let unit = unit.trim();
+    Some(Amount {
+        quantity: Some(quantity.to_string()),
+        unit: (!unit.is_empty()).then(|| unit.to_string()),
+    })
+}
+
+type Validator = fn(&str) -> bool;
+
+/// Well known keys and how to check their values
+const WELL_KNOWN: &[(&str, Validator, &str)] = &[
+    (
+        "servings",
+        |v| parse_servings(v).is_some(),
+        "is not a positive number",
+    ),
+    (
+        "lang",
+        is_language_tag,
+        "is not a BCP 47 language tag, like en-US",
+    ),
+    ("source", is_url, "is not an http or https URL"),
+    (
+        "prep-time",
+        |v| parse_duration(v).is_some(),
+        "is not a duration, like 15 minutes",
+    ),
+    (
+        "cook-time",
+        |v| parse_duration(v).is_some(),
+        "is not a duration, like 15 minutes",
+    ),
+    (
+        "total-time",
+        |v| parse_duration(v).is_some(),
+        "is not a duration, like 15 minutes",
+    ),
+    (
+        "difficulty",
+        |v| Difficulty::parse(v).is_some(),
+        "is not one of easy, medium or hard",
+    ),
+    (
+        "yield",
+        |v| parse_amount(v).is_some(),
+        "is not an amount, like 500 ml or 12 cookies",
+    ),
+    (
+        recipe_parser::VERSION_KEY,
+        |v| v.parse::<SpecVersion>().is_ok_and(|v| v.is_supported()),
+        "is not a known spec version, like 0.2",
+    ),
+];
+
+/// Join the lines of a multi-line value, removing their indentation
+pub fn fold_value(raw: &str) -> String {
+    raw.lines()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Metadata of a recipe, written as `>> key: value`.
+///
+/// All the keys are kept in the order they were written, and the well known ones
+/// can be read with typed accessors. A key can be repeated, and all its values are kept.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Metadata {
+    entries: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Add a value, repeated keys don't replace the previous values
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((key.into(), value.into()));
+    }
+
+    /// The last value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).last().copied()
+    }
+
+    /// All the values of a repeated key
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        let key = normalize_key(key);
+        self.iter()
+            .filter(|(k, _)| normalize_key(k) == key)
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// All the entries, repeated keys appear more than once
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.get("name")
+    }
+
+    pub fn servings(&self) -> Option<u32> {
+        self.get("servings").and_then(parse_servings)
+    }
+
+    /// Tags are separated by commas: `>> tags: vegan, easy`,
+    /// and the key can be repeated to add more tags.
+    pub fn tags(&self) -> Vec<&str> {
+        self.get_all("tags")
+            .into_iter()
+            .flat_map(parse_tags)
+            .collect()
+    }
+
+    /// Language of the recipe as a BCP 47 tag
+    pub fn lang(&self) -> Option<&str> {
+        self.get("lang").filter(|v| is_language_tag(v))
+    }
+
+    /// URL where the recipe comes from
+    pub fn source(&self) -> Option<&str> {
+        self.get("source").filter(|v| is_url(v))
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.get("author")
+    }
+
+    pub fn prep_time(&self) -> Option<Duration> {
+        self.get("prep-time").and_then(parse_duration)
+    }
+
+    pub fn cook_time(&self) -> Option<Duration> {
+        self.get("cook-time").and_then(parse_duration)
+    }
+
+    /// The `total-time`, or the sum of the prep and cook times
+    pub fn total_time(&self) -> Option<Duration> {
+        self.get("total-time").and_then(parse_duration).or_else(|| {
+            match (self.prep_time(), self.cook_time()) {
+                (None, None) => None,
+                (prep, cook) => Some(prep.unwrap_or_default() + cook.unwrap_or_default()),
+            }
+        })
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.get("difficulty").and_then(Difficulty::parse)
+    }
+
+    /// What the recipe produces, like `500 ml` or `12 cookies`
+    pub fn recipe_yield(&self) -> Option<Amount> {
+        self.get("yield").and_then(parse_amount)
+    }
+
+    /// List the well known keys with malformed values
+    pub fn validate(&self) -> Vec<MetadataIssue> {
+        let mut issues: Vec<MetadataIssue> = self
+            .iter()
+            .filter_map(|(key, value)| {
+                let normalized = normalize_key(key);
+                let (_, is_valid, reason) = WELL_KNOWN
+                    .iter()
+                    .find(|(well_known, _, _)| *well_known == normalized)?;
+                (!is_valid(value)).then(|| MetadataIssue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason,
+                })
+            })
+            .collect();
+        issues.sort_by(|a, b| a.key.cmp(&b.key));
+        issues
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metadata(entries: &[(&str, &str)]) -> Metadata {
+        let mut metadata = Metadata::default();
+        for (key, value) in entries {
+            metadata.insert(*key, *value);
+        }
+        metadata
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let metadata = metadata(&[
+            ("name", "hummus"),
+            ("servings", "2"),
+            ("tags", "vegan, easy,"),
+            ("lang", "en-US"),
+            ("source", "https://reciperium.com/hummus"),
+            ("Prep Time", "10 minutes"),
+            ("cook_time", "1 hour"),
+            ("difficulty", "Easy"),
+            ("yield", "500 ml"),
+            ("custom", "kept"),
+        ]);
+        assert_eq!(metadata.name(), Some("hummus"));
+        assert_eq!(metadata.servings(), Some(2));
+        assert_eq!(metadata.tags(), vec!["vegan", "easy"]);
+        assert_eq!(metadata.lang(), Some("en-US"));
+        assert_eq!(metadata.source(), Some("https://reciperium.com/hummus"));
+        assert_eq!(metadata.prep_time(), Some(Duration::from_secs(600)));
+        assert_eq!(metadata.total_time(), Some(Duration::from_secs(4200)));
+        assert_eq!(metadata.difficulty(), Some(Difficulty::Easy));
+        assert_eq!(
+            metadata.recipe_yield().and_then(|amount| amount.unit),
+            Some("ml".to_string())
+        );
+        assert_eq!(metadata.get("custom"), Some("kept"));
+        assert!(metadata.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_malformed_values() {
+        let metadata = metadata(&[
+            ("servings", "two"),
+            ("lang", "english please"),
+            ("source", "my grandma"),
+            ("prep-time", "a while"),
+            ("recp-version", "0.9"),
+            ("custom", "anything goes"),
+        ]);
+        assert_eq!(metadata.servings(), None);
+        assert_eq!(metadata.lang(), None);
+        let issues: Vec<String> = metadata
+            .validate()
+            .into_iter()
+            .map(|issue| issue.key)
+            .collect();
+        assert_eq!(
+            issues,
+            vec!["lang", "prep-time", "recp-version", "servings", "source"]
+        );
+    }
+
+    #[test]
+    fn test_repeated_keys_are_kept() {
+        let metadata = metadata(&[
+            ("tags", "vegan, easy"),
+            ("author", "woile"),
+            ("Tags", "high-protein"),
+            ("author", "grandma"),
+        ]);
+        assert_eq!(metadata.tags(), vec!["vegan", "easy", "high-protein"]);
+        assert_eq!(metadata.get_all("author"), vec!["woile", "grandma"]);
+        assert_eq!(metadata.get("author"), Some("grandma"));
+        assert_eq!(metadata.len(), 4);
+    }
+
+    #[test]
+    fn test_fold_value() {
+        assert_eq!(
+            fold_value("first\n   second\n\tthird"),
+            "first\nsecond\nthird"
+        );
+    }
+
+    #[test]
+    fn test_language_tags() {
+        assert!(is_language_tag("en"));
+        assert!(is_language_tag("zh-Hant-TW"));
+        assert!(is_language_tag("es-419"));
+        assert!(!is_language_tag("en_US"));
+        assert!(!is_language_tag("e"));
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Plurals that don't follow the english rules
+const IRREGULAR: &[(&str, &str)] = &[
+    ("leaves", "leaf"),
+    ("loaves", "loaf"),
+    ("halves", "half"),
+    ("knives", "knife"),
+    ("cookies", "cookie"),
+    ("brownies", "brownie"),
+    ("smoothies", "smoothie"),
+    ("geese", "goose"),
+    ("teeth", "tooth"),
+];
+
+/// English words ending in `s` that are not plurals
+const UNCOUNTABLE: &[&str] = &[
+    "asparagus",
+    "couscous",
+    "hummus",
+    "molasses",
+    "swiss",
+    "anise",
+    "citrus",
+    "octopus",
+    "series",
+];
+
+/// Singular of an english word, e.g: `tomatoes` -> `tomato`, `berries` -> `berry`
+fn singular_en(word: &str) -> String {
+    if UNCOUNTABLE.contains(&word) {
+        return word.to_string();
+    }
+    if let Some((_, singular)) = IRREGULAR.iter().find(|(plural, _)| *plural == word) {
+        return singular.to_string();
+    }
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() > 1) {
+        return format!("{stem}y");
+    }
+    if let Some(stem) = word.strip_suffix("oes") {
+        return format!("{stem}o");
+    }
+    for suffix in ["sses", "ches", "shes", "xes", "zes"] {
+        if word.ends_with(suffix) {
+            return word[..word.len() - 2].to_string();
+        }
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if !stem.ends_with(['s', 'u', 'i']) && stem.chars().count() > 1 => {
+            stem.to_string()
+        }
+        _ => word.to_string(),
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// Singular of a spanish word without accents, e.g: `limones` -> `limon`, `nueces` -> `nuez`
+fn singular_es(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ces") {
+        return format!("{stem}z");
+    }
+    // the words ending in these consonants add `es`: `limon` -> `limones`
+    if let Some(stem) = word.strip_suffix("es")
+        && stem.chars().count() > 2
+        && stem.ends_with(['l', 'n', 'r', 'd', 'j', 'y'])
+    {
+        return stem.to_string();
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if stem.ends_with(is_vowel) && stem.chars().count() > 2 => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+/// Singular of a portuguese word without accents, e.g: `limoes` -> `limao`, `ovos` -> `ovo`
+fn singular_pt(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("oes") {
+        return format!("{stem}ao");
+    }
+    if let Some(stem) = word.strip_suffix("ns") {
+        return format!("{stem}m");
+    }
+    singular_es(word)
+}
+
+/// Singular of a french word without accents, e.g: `gateaux` -> `gateau`, `oeufs` -> `oeuf`
+fn singular_fr(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("aux")
+        && !stem.ends_with('e')
+    {
+        return format!("{stem}al");
+    }
+    match word.strip_suffix(['s', 'x']) {
+        Some(stem) if stem.chars().count() > 2 => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+type Singularize = fn(&str) -> String;
+
+/// Singularization rules by the primary subtag of the language
+const SINGULAR_RULES: &[(&str, Singularize)] = &[
+    ("en", singular_en),
+    ("es", singular_es),
+    ("pt", singular_pt),
+    ("fr", singular_fr),
+];
+
+/// Lower case, compatibility decomposition and no accents: `Crème  Fraîche` -> `creme fraiche`
+fn fold(name: &str) -> String {
+    name.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Turns ingredient names into keys that can be compared.
+///
+/// `{Tomatoes}`, `{tomato}` and `{ToMaToeS}` are the same ingredient, and
+/// with a synonym file `{garbanzos}` can be the same as `{chickpeas}`.
+/// Aggregation, search and pantry matching all compare names through it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Normalizer {
+    /// Normalized alias to its normalized canonical name
+    synonyms: HashMap<String, String>,
+}
+
+impl Normalizer {
+    /// Read a synonym file, with a canonical name and its aliases per line.
+    /// Empty lines and lines starting with `#` are ignored.
+    ///
+    /// ```text
+    /// # canonical = aliases
+    /// chickpeas = garbanzo, garbanzo beans
+    /// ```
+    pub fn parse_synonyms(content: &str) -> Result<Normalizer, String> {
+        let mut normalizer = Normalizer::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((canonical, aliases)) = line.split_once('=') else {
+                return Err(format!(
+                    "line {}: expected `name = alias, alias`",
+                    number + 1
+                ));
+            };
+            for alias in aliases.split(',').filter(|alias| !alias.trim().is_empty()) {
+                normalizer.add_synonym(canonical, alias);
+            }
+        }
+        Ok(normalizer)
+    }
+
+    pub fn load(path: &Path) -> Result<Normalizer, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        Normalizer::parse_synonyms(&content)
+    }
+
+    /// Treat `alias` as the same ingredient as `canonical`
+    pub fn add_synonym(&mut self, canonical: &str, alias: &str) {
+        let canonical = self.singular(canonical, None);
+        self.synonyms.insert(fold(alias), canonical.clone());
+        self.synonyms.insert(self.singular(alias, None), canonical);
+    }
+
+    /// Folded name with the last word in singular, using the rules of the language
+    fn singular(&self, name: &str, lang: Option<&str>) -> String {
+        let mut words: Vec<String> = fold(name).split(' ').map(str::to_string).collect();
+        let lang = lang
+            .and_then(|lang| lang.split('-').next())
+            .unwrap_or("en")
+            .to_lowercase();
+        let rule = SINGULAR_RULES
+            .iter()
+            .find(|(rule_lang, _)| *rule_lang == lang)
+            .map(|(_, rule)| rule);
+        if let (Some(last), Some(rule)) = (words.last_mut(), rule) {
+            *last = rule(last);
+        }
+        words.join(" ")
+    }
+
+    /// The key of an ingredient name, `lang` is a BCP 47 tag and english is the default
+    pub fn normalize(&self, name: &str, lang: Option<&str>) -> String {
+        let key = self.singular(name, lang);
+        match self
+            .synonyms
+            .get(&key)
+            .or_else(|| self.synonyms.get(&fold(name)))
+        {
+            Some(canonical) => canonical.clone(),
+            None => key,
+        }
+    }
+}
+
+/// Normalize an english ingredient name, without synonyms
+pub fn normalize_name(name: &str) -> String {
+    Normalizer::default().normalize(name, None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Tomatoes"), "tomato");
+        assert_eq!(normalize_name("ToMaToeS"), "tomato");
+        assert_eq!(normalize_name("  boiled   chickpeas "), "boiled chickpea");
+        assert_eq!(normalize_name("olive oil"), "olive oil");
+        assert_eq!(normalize_name("lentils"), "lentil");
+        assert_eq!(normalize_name("berries"), "berry");
+        assert_eq!(normalize_name("bay leaves"), "bay leaf");
+        assert_eq!(normalize_name("peaches"), "peach");
+        assert_eq!(normalize_name("hummus"), "hummus");
+        assert_eq!(normalize_name("cheeses"), "cheese");
+        assert_eq!(normalize_name("glass"), "glass");
+    }
+
+    #[test]
+    fn test_unicode_folding() {
+        assert_eq!(normalize_name("Crème Fraîche"), "creme fraiche");
+        // composed and decomposed accents
+        assert_eq!(
+            normalize_name("jalape\u{00f1}o"),
+            normalize_name("jalapen\u{0303}o")
+        );
+        assert_eq!(normalize_name("ＴＯＦＵ"), "tofu");
+    }
+
+    #[test]
+    fn test_singular_by_language() {
+        let normalizer = Normalizer::default();
+        let cases = [
+            ("es", "limones", "limon"),
+            ("es", "nueces", "nuez"),
+            ("es", "tomates", "tomate"),
+            ("es-AR", "papas", "papa"),
+            ("es", "ajo", "ajo"),
+            ("es", "panes", "pan"),
+            ("pt", "limões", "limao"),
+            ("pt", "ovos", "ovo"),
+            ("fr", "gâteaux", "gateau"),
+            ("fr", "œufs", "œuf"),
+            ("fr", "chevaux", "cheval"),
+            ("de", "Eier", "eier"),
+        ];
+        for (lang, name, expected) in cases {
+            assert_eq!(normalizer.normalize(name, Some(lang)), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_synonyms() {
+        let normalizer =
+            Normalizer::parse_synonyms("# legumes\nchickpeas = garbanzo, Garbanzo Beans\n\n")
+                .unwrap();
+        assert_eq!(normalizer.normalize("garbanzos", None), "chickpea");
+        assert_eq!(normalizer.normalize("garbanzo beans", None), "chickpea");
+        assert_eq!(normalizer.normalize("Chickpeas", None), "chickpea");
+        assert!(Normalizer::parse_synonyms("chickpeas garbanzo").is_err());
+    }
+}
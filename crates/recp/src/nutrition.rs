@@ -0,0 +1,301 @@
+use std::fs;
+use std::ops::{Add, Mul};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Recipe;
+use crate::metadata::parse_amount;
+use crate::names::Normalizer;
+use crate::recipe::{Amount, Ingredient};
+use crate::units::Dimension;
+
+/// Energy and macronutrients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Nutrients {
+    /// Kilocalories
+    pub calories: f64,
+    /// Grams of protein
+    pub protein: f64,
+    /// Grams of fat
+    pub fat: f64,
+    /// Grams of carbohydrates
+    pub carbs: f64,
+}
+
+impl Add for Nutrients {
+    type Output = Nutrients;
+
+    fn add(self, other: Nutrients) -> Nutrients {
+        Nutrients {
+            calories: self.calories + other.calories,
+            protein: self.protein + other.protein,
+            fat: self.fat + other.fat,
+            carbs: self.carbs + other.carbs,
+        }
+    }
+}
+
+impl Mul<f64> for Nutrients {
+    type Output = Nutrients;
+
+    fn mul(self, factor: f64) -> Nutrients {
+        Nutrients {
+            calories: self.calories * factor,
+            protein: self.protein * factor,
+            fat: self.fat * factor,
+            carbs: self.carbs * factor,
+        }
+    }
+}
+
+/// A food of the nutrient table, its nutrients are per 100 grams
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Food {
+    pub name: String,
+    #[serde(flatten)]
+    pub nutrients: Nutrients,
+    /// Grams per milliliter, to convert volumes to mass
+    #[serde(default)]
+    pub density: Option<f64>,
+    /// Grams of a single piece, to convert counts to mass, e.g: an egg
+    #[serde(default)]
+    pub piece_weight: Option<f64>,
+}
+
+/// Nutrients of the foods, provided by the user.
+///
+/// It can be a CSV with the headers `name,calories,protein,fat,carbs,density,piece_weight`,
+/// or a JSON list of objects with the same keys. `density` and `piece_weight` are optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NutrientTable {
+    pub foods: Vec<Food>,
+}
+
+impl NutrientTable {
+    pub fn parse_csv(content: &str) -> Result<NutrientTable, String> {
+        let foods = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<Food>, _>>()
+            .map_err(|err| format!("invalid nutrient table: {err}"))?;
+        Ok(NutrientTable { foods })
+    }
+
+    pub fn parse_json(content: &str) -> Result<NutrientTable, String> {
+        let foods = serde_json::from_str(content)
+            .map_err(|err| format!("invalid nutrient table: {err}"))?;
+        Ok(NutrientTable { foods })
+    }
+
+    /// Read a `.json` or `.csv` file
+    pub fn load(path: &Path) -> Result<NutrientTable, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => NutrientTable::parse_json(&content),
+            _ => NutrientTable::parse_csv(&content),
+        }
+    }
+
+    /// Find a food by its normalized name
+    pub fn get(&self, name: &str, normalizer: &Normalizer, lang: Option<&str>) -> Option<&Food> {
+        let name = normalizer.normalize(name, lang);
+        self.foods
+            .iter()
+            .find(|food| normalizer.normalize(&food.name, lang) == name)
+    }
+}
+
+/// Nutrients of an ingredient of the recipe
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngredientNutrition {
+    pub name: String,
+    pub grams: f64,
+    pub nutrients: Nutrients,
+}
+
+/// An ingredient left out of the totals, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unresolved {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NutritionReport {
+    pub total: Nutrients,
+    pub servings: Option<u32>,
+    pub per_serving: Option<Nutrients>,
+    pub ingredients: Vec<IngredientNutrition>,
+    /// Ingredients not found in the nutrient table
+    pub unmatched: Vec<Unresolved>,
+    /// Ingredients found, but their amount couldn't be converted to grams
+    pub unconvertible: Vec<Unresolved>,
+}
+
+/// The override of an ingredient, written in the metadata as `nutrition.<ingredient>`.
+///
+/// The value is either a mass used instead of the amount, like `>> nutrition.garlic: 5 g`,
+/// or the name of the food in the table, like `>> nutrition.garlic: garlic, raw`.
+enum Override {
+    Grams(f64),
+    Food(String),
+}
+
+fn find_override(recipe: &Recipe, ingredient: &Ingredient) -> Option<Override> {
+    let value = recipe
+        .metadata
+        .get(&format!("nutrition.{}", ingredient.name))?;
+    match parse_amount(value).and_then(|amount| amount.to_base()) {
+        Some((grams, Dimension::Mass)) => Some(Override::Grams(grams)),
+        _ => Some(Override::Food(value.to_string())),
+    }
+}
+
+/// Grams of the first amount that can be converted to mass
+fn grams(ingredient: &Ingredient, food: &Food) -> Option<f64> {
+    ingredient
+        .amounts()
+        .filter_map(|amount: Amount| amount.to_base())
+        .find_map(|(value, dimension)| match dimension {
+            Dimension::Mass => Some(value),
+            Dimension::Volume => food.density.map(|density| value * density),
+            Dimension::Count => food.piece_weight.map(|weight| value * weight),
+        })
+}
+
+/// Compute the nutrients of a recipe, per recipe and per serving.
+///
+/// Optional ingredients are left out. Referenced recipes are reported as unmatched.
+pub fn nutrition(
+    recipe: &Recipe,
+    table: &NutrientTable,
+    normalizer: &Normalizer,
+) -> NutritionReport {
+    let lang = recipe.metadata.lang();
+    let mut total = Nutrients::default();
+    let mut ingredients = Vec::new();
+    let mut unmatched = Vec::new();
+    let mut unconvertible = Vec::new();
+    for ingredient in recipe.ingredients.iter().filter(|i| !i.optional) {
+        let unresolved = |reason: &str| Unresolved {
+            name: ingredient.name.clone(),
+            reason: reason.to_string(),
+        };
+        let over = find_override(recipe, ingredient);
+        let food_name = match &over {
+            Some(Override::Food(name)) => name.as_str(),
+            _ => ingredient.name.as_str(),
+        };
+        let Some(food) = table.get(food_name, normalizer, lang) else {
+            unmatched.push(unresolved("not found in the nutrient table"));
+            continue;
+        };
+        let grams = match over {
+            Some(Override::Grams(grams)) => Some(grams),
+            _ => grams(ingredient, food),
+        };
+        let Some(grams) = grams else {
+            let reason = if ingredient.quantity.is_none() {
+                "has no amount"
+            } else {
+                "amount can't be converted to grams"
+            };
+            unconvertible.push(unresolved(reason));
+            continue;
+        };
+        let nutrients = food.nutrients * (grams / 100.0);
+        total = total + nutrients;
+        ingredients.push(IngredientNutrition {
+            name: ingredient.name.clone(),
+            grams,
+            nutrients,
+        });
+    }
+    for recipe_ref in &recipe.recipes_refs {
+        unmatched.push(Unresolved {
+            name: recipe_ref.name.clone(),
+            reason: "is a referenced recipe".to_string(),
+        });
+    }
+    let servings = recipe.metadata.servings();
+    NutritionReport {
+        total,
+        servings,
+        per_serving: servings.map(|servings| total * (1.0 / servings as f64)),
+        ingredients,
+        unmatched,
+        unconvertible,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TABLE: &str = "name,calories,protein,fat,carbs,density,piece_weight
+chickpeas,164,8.9,2.6,27.4,,
+olive oil,884,0,100,0,0.91,
+egg,143,12.6,9.5,0.7,,50
+garlic,149,6.4,0.5,33,,
+";
+
+    #[test]
+    fn test_parse_tables() {
+        let csv = NutrientTable::parse_csv(TABLE).unwrap();
+        assert_eq!(csv.foods.len(), 4);
+        assert_eq!(csv.foods[2].piece_weight, Some(50.0));
+        let json = NutrientTable::parse_json(
+            r#"[{"name": "egg", "calories": 143, "protein": 12.6, "fat": 9.5, "carbs": 0.7, "piece_weight": 50}]"#,
+        )
+        .unwrap();
+        assert_eq!(json.foods[0], csv.foods[2]);
+        assert!(NutrientTable::parse_csv("name,calories\negg,abc").is_err());
+    }
+
+    #[test]
+    fn test_nutrition() {
+        let table = NutrientTable::parse_csv(TABLE).unwrap();
+        let recipe = Recipe::try_from(
+            ">> servings: 2\nMix {chickpeas}(1 can | 200 gr), {olive oil}(10 ml), {eggs}(2), {salt}, {tahini}(2 tsp) and {chives}?(1 tbsp)",
+        )
+        .unwrap();
+        let report = nutrition(&recipe, &table, &Normalizer::default());
+        let grams: Vec<_> = report
+            .ingredients
+            .iter()
+            .map(|i| (i.name.as_str(), (i.grams * 10.0).round() / 10.0))
+            .collect();
+        assert_eq!(
+            grams,
+            vec![("chickpeas", 200.0), ("olive oil", 9.1), ("eggs", 100.0)]
+        );
+        assert_eq!(
+            report
+                .unmatched
+                .iter()
+                .map(|u| u.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["salt", "tahini"]
+        );
+        assert_eq!(report.unconvertible, vec![]);
+        let calories = 164.0 * 2.0 + 884.0 * 0.091 + 143.0;
+        assert!((report.total.calories - calories).abs() < 0.01);
+        assert!((report.per_serving.unwrap().calories - calories / 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_nutrition_overrides() {
+        let table = NutrientTable::parse_csv(TABLE).unwrap();
+        let recipe = Recipe::try_from(
+            ">> nutrition.cloves: garlic\n>> nutrition.chickpeas: 100 g\nMix {chickpeas}(1 can) and {cloves}(2)",
+        )
+        .unwrap();
+        let report = nutrition(&recipe, &table, &Normalizer::default());
+        assert_eq!(report.ingredients[0].grams, 100.0);
+        assert_eq!(report.unconvertible[0].name, "cloves");
+        assert_eq!(report.per_serving, None);
+    }
+}
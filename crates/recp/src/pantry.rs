@@ -0,0 +1,304 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::Recipe;
+use crate::metadata::parse_amount;
+use crate::names::Normalizer;
+use crate::recipe::Amount;
+use crate::units::{Dimension, format_quantity};
+
+/// Something we have at home, with an optional amount
+#[derive(Debug, Clone, PartialEq)]
+pub struct PantryItem {
+    pub name: String,
+    pub amount: Option<Amount>,
+}
+
+/// The ingredients available to cook.
+///
+/// A pantry file has one ingredient per line, optionally followed by
+/// its amount after a colon. Empty lines and lines starting with `#` are ignored.
+///
+/// ```text
+/// # fridge
+/// tofu: 400 gr
+/// eggs: 6
+/// salt
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pantry {
+    pub items: Vec<PantryItem>,
+}
+
+impl Pantry {
+    pub fn parse(content: &str) -> Result<Pantry, String> {
+        let mut items = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, amount) = match line.split_once(':') {
+                Some((name, amount)) => (
+                    name.trim(),
+                    Some(parse_amount(amount).ok_or_else(|| {
+                        format!(
+                            "line {}: {:?} is not an amount, like 400 gr",
+                            number + 1,
+                            amount.trim()
+                        )
+                    })?),
+                ),
+                None => (line, None),
+            };
+            if name.is_empty() {
+                return Err(format!("line {}: missing the ingredient name", number + 1));
+            }
+            items.push(PantryItem {
+                name: name.to_string(),
+                amount,
+            });
+        }
+        Ok(Pantry { items })
+    }
+
+    pub fn load(path: &Path) -> Result<Pantry, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        Pantry::parse(&content)
+    }
+
+    /// Find an item by its normalized name, `lang` is the language of both names
+    pub fn get(
+        &self,
+        name: &str,
+        normalizer: &Normalizer,
+        lang: Option<&str>,
+    ) -> Option<&PantryItem> {
+        let name = normalizer.normalize(name, lang);
+        self.items
+            .iter()
+            .find(|item| normalizer.normalize(&item.name, lang) == name)
+    }
+}
+
+/// An ingredient of the recipe we don't have, or don't have enough of
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Missing {
+    pub name: String,
+    /// What we lack, when the amounts can be compared, e.g: `200 g`
+    pub amount: Option<String>,
+}
+
+/// How well a recipe can be cooked with a pantry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub path: PathBuf,
+    pub name: Option<String>,
+    /// Ingredients we have enough of
+    pub available: Vec<String>,
+    pub missing: Vec<Missing>,
+}
+
+impl Suggestion {
+    /// Fraction of the ingredients we have, from 0 to 1
+    pub fn coverage(&self) -> f64 {
+        let total = self.available.len() + self.missing.len();
+        if total == 0 {
+            return 1.0;
+        }
+        self.available.len() as f64 / total as f64
+    }
+}
+
+/// Format a value in the base unit of its dimension, e.g: `200 g`
+fn format_base(value: f64, dimension: Dimension) -> String {
+    let symbol = match dimension {
+        Dimension::Mass => " g",
+        Dimension::Volume => " ml",
+        Dimension::Count => "",
+    };
+    format!("{}{symbol}", format_quantity(value))
+}
+
+/// What a recipe needs of an ingredient, adding up all the times it's used
+struct Need {
+    name: String,
+    /// Total in the base unit of the dimension, when all the uses can be added
+    total: Option<(f64, Dimension)>,
+    /// The amount as written, when the ingredient is used once
+    written: Option<String>,
+}
+
+fn needs(recipe: &Recipe, normalizer: &Normalizer) -> Vec<Need> {
+    let mut needs: Vec<(String, Need)> = Vec::new();
+    for ingredient in recipe.ingredients.iter().filter(|i| !i.optional) {
+        let key = recipe.normalized_name(&ingredient.name, normalizer);
+        let amount = ingredient.amounts().find_map(|amount| amount.to_base());
+        match needs.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, need)) => {
+                need.written = None;
+                need.total = match (need.total, amount) {
+                    (Some((total, dimension)), Some((value, other))) if dimension == other => {
+                        Some((total + value, dimension))
+                    }
+                    _ => None,
+                }
+            }
+            None => needs.push((
+                key,
+                Need {
+                    name: ingredient.name.clone(),
+                    total: amount,
+                    written: ingredient
+                        .quantity
+                        .as_ref()
+                        .map(|quantity| match &ingredient.unit {
+                            Some(unit) => format!("{quantity} {unit}"),
+                            None => quantity.clone(),
+                        }),
+                },
+            )),
+        }
+    }
+    needs.into_iter().map(|(_, need)| need).collect()
+}
+
+/// Compare a recipe with the pantry.
+///
+/// Optional ingredients are not required. When both the recipe and the pantry
+/// have amounts of the same dimension, a lower amount in the pantry is missing.
+pub fn check(
+    pantry: &Pantry,
+    normalizer: &Normalizer,
+    path: impl Into<PathBuf>,
+    recipe: &Recipe,
+) -> Suggestion {
+    let mut available = Vec::new();
+    let mut missing = Vec::new();
+    for need in needs(recipe, normalizer) {
+        let Some(item) = pantry.get(&need.name, normalizer, recipe.metadata.lang()) else {
+            missing.push(Missing {
+                amount: need.written.or_else(|| {
+                    need.total
+                        .map(|(value, dimension)| format_base(value, dimension))
+                }),
+                name: need.name,
+            });
+            continue;
+        };
+        let stock = item.amount.as_ref().and_then(Amount::to_base);
+        match (need.total, stock) {
+            (Some((needed, dimension)), Some((stock, other)))
+                if dimension == other && stock < needed =>
+            {
+                missing.push(Missing {
+                    name: need.name,
+                    amount: Some(format_base(needed - stock, dimension)),
+                });
+            }
+            _ => available.push(need.name),
+        }
+    }
+    Suggestion {
+        path: path.into(),
+        name: recipe.name.clone(),
+        available,
+        missing,
+    }
+}
+
+/// Rank the recipes by how much of them can be cooked with the pantry,
+/// the ones missing fewer ingredients first.
+pub fn suggest<'a>(
+    pantry: &Pantry,
+    normalizer: &Normalizer,
+    recipes: impl IntoIterator<Item = (PathBuf, &'a Recipe<'a>)>,
+) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = recipes
+        .into_iter()
+        .map(|(path, recipe)| check(pantry, normalizer, path, recipe))
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.coverage()
+            .total_cmp(&a.coverage())
+            .then(a.missing.len().cmp(&b.missing.len()))
+            .then(a.path.cmp(&b.path))
+    });
+    suggestions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_pantry() {
+        let pantry = Pantry::parse("# fridge\nTofu: 400 gr\n\neggs: 6\nsalt").unwrap();
+        assert_eq!(pantry.items.len(), 3);
+        let normalizer = Normalizer::default();
+        let get = |name| pantry.get(name, &normalizer, None).unwrap();
+        assert_eq!(get("tofu").name, "Tofu");
+        assert_eq!(get("egg").amount.as_ref().unwrap().value(), Some(6.0));
+        assert_eq!(get("salt").amount, None);
+        assert!(Pantry::parse("tofu: a lot").is_err());
+    }
+
+    #[test]
+    fn test_check_amounts() {
+        let pantry = Pantry::parse("tofu: 150 gr\ntomatoes: 3\nsalt").unwrap();
+        let recipe = Recipe::try_from(
+            "Fry {tofu}(100 gr) with {tomato}(2) and more {tofu}(100 gr), {salt}, {rice}(200 gr) and {chives}?()",
+        )
+        .unwrap();
+        let suggestion = check(&pantry, &Normalizer::default(), "tofu.recp", &recipe);
+        assert_eq!(suggestion.available, vec!["tomato", "salt"]);
+        assert_eq!(
+            suggestion.missing,
+            vec![
+                Missing {
+                    name: "tofu".to_string(),
+                    amount: Some("50 g".to_string())
+                },
+                Missing {
+                    name: "rice".to_string(),
+                    amount: Some("200 gr".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_with_synonyms_and_language() {
+        let pantry = Pantry::parse("garbanzo\nlimón").unwrap();
+        let normalizer = Normalizer::parse_synonyms("garbanzo = chickpeas").unwrap();
+        let recipe =
+            Recipe::try_from(">> lang: es\nMezclar {Chickpeas}(400 gr) y {limones}(2)").unwrap();
+        let suggestion = check(&pantry, &normalizer, "hummus.recp", &recipe);
+        assert_eq!(suggestion.available, vec!["Chickpeas", "limones"]);
+        assert!(suggestion.missing.is_empty());
+    }
+
+    #[test]
+    fn test_suggest_ranking() {
+        let pantry = Pantry::parse("eggs\nmilk").unwrap();
+        let omelette = Recipe::try_from("Beat {eggs}(2) with {milk}").unwrap();
+        let cake = Recipe::try_from("Mix {eggs}(2), {milk} and {flour}").unwrap();
+        let suggestions = suggest(
+            &pantry,
+            &Normalizer::default(),
+            [
+                (PathBuf::from("cake.recp"), &cake),
+                (PathBuf::from("omelette.recp"), &omelette),
+            ],
+        );
+        let paths: Vec<_> = suggestions.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("omelette.recp"), PathBuf::from("cake.recp")]
+        );
+        assert_eq!(suggestions[1].missing[0].name, "flour");
+    }
+}
@@ -1,68 +1,310 @@
-use std::collections::HashMap;
+use recipe_parser::{TemperatureScale, Token, parse, unescape};
+use serde::Serialize;
 
-use recipe_parser::{Token, parse};
+use crate::locale::Locale;
+use crate::metadata::{Metadata, fold_value};
+use crate::names::Normalizer;
+use crate::units::{
+    Dimension, convert_temperature, format_quantity, format_temperature, lookup_unit,
+    parse_quantity,
+};
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct Amount {
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+}
+
+impl Amount {
+    /// The quantity as a number, if it can be parsed
+    pub fn value(&self) -> Option<f64> {
+        self.quantity.as_deref().and_then(parse_quantity)
+    }
+
+    /// What the unit measures, `None` when the unit is not known, e.g: `can`
+    pub fn dimension(&self) -> Option<Dimension> {
+        match &self.unit {
+            Some(unit) => lookup_unit(unit).map(|unit| unit.dimension),
+            None => Some(Dimension::Count),
+        }
+    }
+
+    /// The value in the base unit of its dimension: grams, milliliters or pieces
+    pub fn to_base(&self) -> Option<(f64, Dimension)> {
+        let value = self.value()?;
+        match &self.unit {
+            Some(unit) => lookup_unit(unit).map(|unit| (value * unit.factor, unit.dimension)),
+            None => Some((value, Dimension::Count)),
+        }
+    }
+
+    /// Convert the amount to another unit of the same dimension
+    pub fn convert_to(&self, unit: &str) -> Option<f64> {
+        let (value, dimension) = self.to_base()?;
+        let target = lookup_unit(unit)?;
+        (target.dimension == dimension).then(|| value / target.factor)
+    }
+
+    /// Multiply the quantity, amounts without a numeric quantity are kept as they are
+    pub fn scale(&self, factor: f64) -> Amount {
+        Amount {
+            quantity: self
+                .value()
+                .map(|value| format_quantity(value * factor))
+                .or_else(|| self.quantity.clone()),
+            unit: self.unit.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub struct Ingredient {
     pub name: String,
     pub quantity: Option<String>,
     pub unit: Option<String>,
+    /// Other amounts that can be used instead of the primary one
+    pub alternatives: Vec<Amount>,
+    pub note: Option<String>,
+    pub optional: bool,
 }
 
-type RecipeRef = Ingredient;
+impl Ingredient {
+    /// The primary amount, the first one written
+    pub fn amount(&self) -> Amount {
+        Amount {
+            quantity: self.quantity.clone(),
+            unit: self.unit.clone(),
+        }
+    }
+
+    /// The primary amount followed by the alternatives
+    pub fn amounts(&self) -> impl Iterator<Item = Amount> + '_ {
+        std::iter::once(self.amount()).chain(self.alternatives.iter().cloned())
+    }
+
+    /// The first amount that can be expressed in the given dimension.
+    ///
+    /// For `{chickpeas}(1 can | 400 gr)`, asking for `Dimension::Mass` returns `400 gr`.
+    pub fn amount_in(&self, dimension: Dimension) -> Option<Amount> {
+        self.amounts()
+            .find(|amount| amount.value().is_some() && amount.dimension() == Some(dimension))
+    }
+
+    /// Remove the thousands separators of the quantities, as written in the language
+    fn normalize_quantities(&mut self, locale: &Locale) {
+        for quantity in std::iter::once(&mut self.quantity)
+            .chain(
+                self.alternatives
+                    .iter_mut()
+                    .map(|amount| &mut amount.quantity),
+            )
+            .flatten()
+        {
+            *quantity = locale.normalize_quantity(quantity);
+        }
+    }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+    /// Scale the primary amount and all the alternatives
+    pub fn scale(&self, factor: f64) -> Ingredient {
+        let amount = self.amount().scale(factor);
+        Ingredient {
+            quantity: amount.quantity,
+            unit: amount.unit,
+            alternatives: self
+                .alternatives
+                .iter()
+                .map(|amount| amount.scale(factor))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+pub type RecipeRef = Ingredient;
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub struct Timer {
     pub duration: String,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Temperature {
+    pub value: String,
+    pub scale: TemperatureScale,
+}
+
+impl Temperature {
+    /// The value as a number, if it can be parsed
+    pub fn degrees(&self) -> Option<f64> {
+        parse_quantity(&self.value)
+    }
+
+    /// The same temperature in another scale, rounded to whole degrees
+    pub fn convert_to(&self, scale: TemperatureScale) -> Option<Temperature> {
+        let value = convert_temperature(self.degrees()?, self.scale, scale)?;
+        Some(Temperature {
+            value: format_temperature(value, scale),
+            scale,
+        })
+    }
+}
+
+impl std::fmt::Display for Temperature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.scale {
+            TemperatureScale::GasMark => write!(f, "gas mark {}", self.value),
+            scale => write!(f, "{} °{scale}", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub struct Material {
     pub name: String,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// A group of ingredients and steps, started by a `== name ==` heading.
+///
+/// The tokens before the first heading belong to a section without name.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct Section<'a> {
+    pub name: Option<String>,
+    pub ingredients: Vec<Ingredient>,
+    pub recipes_refs: Vec<RecipeRef>,
+    pub timers: Vec<Timer>,
+    pub temperatures: Vec<Temperature>,
+    pub materials: Vec<Material>,
+    pub instructions: Vec<Token<'a>>,
+}
+
+impl Section<'_> {
+    /// A section without name and without steps, e.g: only metadata before the first heading
+    fn is_blank(&self) -> bool {
+        self.name.is_none()
+            && self.instructions.iter().all(|token| {
+                matches!(
+                    token,
+                    Token::Space(_)
+                        | Token::Metadata { .. }
+                        | Token::Comment(_)
+                        | Token::FrontMatter { .. }
+                        | Token::Backstory(_)
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub struct Recipe<'a> {
     pub name: Option<String>,
-    pub metadata: HashMap<String, String>,
+    pub metadata: Metadata,
     pub ingredients: Vec<Ingredient>,
     pub recipes_refs: Vec<RecipeRef>,
     pub timers: Vec<Timer>,
+    pub temperatures: Vec<Temperature>,
     pub materials: Vec<Material>,
     pub backstory: Option<String>,
     pub instructions: Vec<Token<'a>>,
+    /// The same ingredients, timers, materials and instructions, grouped by section
+    pub sections: Vec<Section<'a>>,
+}
+
+impl Recipe<'_> {
+    /// The recipe uses `== name ==` headings
+    pub fn has_sections(&self) -> bool {
+        self.sections.iter().any(|section| section.name.is_some())
+    }
+
+    /// Convert the temperatures to another scale, the ones without a conversion are kept
+    pub fn convert_temperatures(&mut self, scale: TemperatureScale) {
+        let section_temperatures = self
+            .sections
+            .iter_mut()
+            .flat_map(|section| section.temperatures.iter_mut());
+        for temperature in self.temperatures.iter_mut().chain(section_temperatures) {
+            if let Some(converted) = temperature.convert_to(scale) {
+                *temperature = converted;
+            }
+        }
+    }
+
+    /// Key to compare an ingredient name, using the language of the recipe
+    pub fn normalized_name(&self, name: &str, normalizer: &Normalizer) -> String {
+        normalizer.normalize(name, self.metadata.lang())
+    }
 }
 
 impl<'a> TryFrom<&'a str> for Recipe<'a> {
     type Error = String;
 
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
-        let tokens = parse(value.trim());
+        let tokens = parse(value.trim()).map_err(|err| err.to_string());
+        #[cfg(feature = "front-matter")]
+        let tokens = tokens.and_then(|tokens| {
+            crate::front_matter::without_invalid_front_matter(value.trim(), tokens)
+        });
         match tokens {
             Ok(tokens) => {
-                let mut metadata = HashMap::new();
+                let mut metadata = Metadata::default();
 
                 let mut ingredients = Vec::new();
                 let mut recipes_refs = Vec::new();
                 let mut timers = Vec::new();
+                let mut temperatures = Vec::new();
                 let mut materials = Vec::new();
                 let mut backstory = String::new();
+                let mut sections = vec![Section::default()];
 
                 for token in tokens.clone() {
+                    if let Token::Section(name) = token {
+                        sections.push(Section {
+                            name: Some(unescape(name).into_owned()),
+                            ..Default::default()
+                        });
+                        continue;
+                    }
+                    let section = sections.last_mut().expect("there's always a section");
+                    section.instructions.push(token.clone());
                     match token {
+                        #[cfg(feature = "front-matter")]
+                        Token::FrontMatter { format, content } => {
+                            for (key, value) in crate::front_matter::entries(format, content)? {
+                                metadata.insert(key, value);
+                            }
+                        }
+                        #[cfg(not(feature = "front-matter"))]
+                        Token::FrontMatter { .. } => {
+                            return Err(
+                                "front matter is not supported, enable the `front-matter` feature"
+                                    .to_string(),
+                            );
+                        }
                         Token::Metadata { key, value } => {
-                            metadata.insert(key.to_string(), value.to_string());
+                            metadata.insert(key.trim(), fold_value(value));
                         }
                         Token::Ingredient {
                             name,
                             quantity,
                             unit,
+                            alternatives,
+                            note,
+                            optional,
                         } => {
                             let i = Ingredient {
-                                name: name.to_string(),
+                                name: unescape(name).into_owned(),
                                 quantity: quantity.map(|v| v.to_string()),
                                 unit: unit.map(|v| v.to_string()),
+                                alternatives: alternatives
+                                    .into_iter()
+                                    .map(|amount| Amount {
+                                        quantity: amount.quantity.map(|v| v.to_string()),
+                                        unit: amount.unit.map(|v| v.to_string()),
+                                    })
+                                    .collect(),
+                                note: note.map(|v| unescape(v).into_owned()),
+                                optional,
                             };
+                            section.ingredients.push(i.clone());
                             ingredients.push(i);
                         }
                         Token::RecipeRef {
@@ -71,28 +313,70 @@ impl<'a> TryFrom<&'a str> for Recipe<'a> {
                             unit,
                         } => {
                             let i = RecipeRef {
-                                name: name.to_string(),
+                                name: unescape(name).into_owned(),
                                 quantity: quantity.map(|v| v.to_string()),
                                 unit: unit.map(|v| v.to_string()),
+                                alternatives: vec![],
+                                note: None,
+                                optional: false,
                             };
+                            section.recipes_refs.push(i.clone());
                             recipes_refs.push(i);
                         }
 
-                        Token::Timer(t) => timers.push(Timer {
-                            duration: t.to_string(),
-                        }),
-                        Token::Material(material) => materials.push(Material {
-                            name: material.to_string(),
-                        }),
+                        Token::Timer(t) => {
+                            let timer = Timer {
+                                duration: unescape(t).into_owned(),
+                            };
+                            section.timers.push(timer.clone());
+                            timers.push(timer);
+                        }
+                        Token::Temperature { value, scale } => {
+                            let temperature = Temperature {
+                                value: value.to_string(),
+                                scale,
+                            };
+                            section.temperatures.push(temperature.clone());
+                            temperatures.push(temperature);
+                        }
+                        Token::Material(material) => {
+                            let material = Material {
+                                name: unescape(material).into_owned(),
+                            };
+                            section.materials.push(material.clone());
+                            materials.push(material);
+                        }
                         Token::Backstory(bs) => backstory.push_str(bs),
                         _ => {}
                     };
                 }
-                let name = metadata.get("name").cloned();
+                if sections[0].is_blank() {
+                    sections.remove(0);
+                }
+                // `lang` can be written after the ingredients. Without it, `1,500`
+                // keeps meaning one and a half, like before languages were supported
+                if let Some(lang) = metadata.lang() {
+                    let locale = Locale::get(Some(lang));
+                    let section_ingredients = sections.iter_mut().flat_map(|section| {
+                        section
+                            .ingredients
+                            .iter_mut()
+                            .chain(section.recipes_refs.iter_mut())
+                    });
+                    for ingredient in ingredients
+                        .iter_mut()
+                        .chain(recipes_refs.iter_mut())
+                        .chain(section_ingredients)
+                    {
+                        ingredient.normalize_quantities(locale);
+                    }
+                }
+                let name = metadata.name().map(|name| name.to_string());
                 Ok(Self {
                     name,
                     ingredients,
                     timers,
+                    temperatures,
                     materials,
                     metadata,
                     recipes_refs,
@@ -104,16 +388,18 @@ impl<'a> TryFrom<&'a str> for Recipe<'a> {
                         }
                     },
                     instructions: tokens,
+                    sections,
                 })
             }
-            Err(err) => Err(err.to_string()),
+            Err(err) => Err(err),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Recipe;
+    use super::{Recipe, Temperature, TemperatureScale};
+    use crate::units::Dimension;
 
     #[test]
     fn test_invalid_recipes() {
@@ -124,4 +410,152 @@ mod test {
         let recipe = Recipe::try_from(invalid_recipe);
         println!("{recipe:?}");
     }
+
+    #[test]
+    fn test_names_are_unescaped() {
+        let recipe = Recipe::try_from(r"Add {salt \& pepper \{fine\}}(1 pinch)").unwrap();
+        assert_eq!(recipe.ingredients[0].name, "salt & pepper {fine}");
+    }
+
+    #[test]
+    fn test_ingredient_note_and_optional() {
+        let recipe = Recipe::try_from("Add {onion}(1, finely chopped) and {salt}?()").unwrap();
+        assert_eq!(
+            recipe.ingredients[0].note.as_deref(),
+            Some("finely chopped")
+        );
+        assert!(!recipe.ingredients[0].optional);
+        assert_eq!(recipe.ingredients[1].note, None);
+        assert!(recipe.ingredients[1].optional);
+    }
+
+    #[test]
+    fn test_quantities_follow_the_language() {
+        let recipe = Recipe::try_from(
+            "Mezclar {harina}(1.000 gramos | 2 tazas) y {sal}(1,5 g)\n>> lang: es",
+        )
+        .unwrap();
+        assert_eq!(recipe.ingredients[0].amount().value(), Some(1000.0));
+        assert_eq!(
+            recipe.ingredients[0].amount_in(Dimension::Volume),
+            Some(super::Amount {
+                quantity: Some("2".to_string()),
+                unit: Some("tazas".to_string())
+            })
+        );
+        assert_eq!(recipe.ingredients[1].amount().value(), Some(1.5));
+        let recipe = Recipe::try_from("Mix {flour}(1,000 g)\n>> lang: en").unwrap();
+        assert_eq!(recipe.ingredients[0].amount().value(), Some(1000.0));
+        // without a language, the comma is a decimal separator
+        let recipe = Recipe::try_from("Mix {flour}(1,500 g)").unwrap();
+        assert_eq!(recipe.ingredients[0].amount().value(), Some(1.5));
+    }
+
+    #[test]
+    fn test_multiline_and_repeated_metadata() {
+        let recipe = Recipe::try_from(
+            ">> tags: vegan\n>> description: a long\n   description\n>> tags: easy\n\nCook\n>> author: woile",
+        )
+        .unwrap();
+        assert_eq!(recipe.metadata.tags(), vec!["vegan", "easy"]);
+        assert_eq!(
+            recipe.metadata.get("description"),
+            Some("a long\ndescription")
+        );
+        assert_eq!(recipe.metadata.author(), Some("woile"));
+    }
+
+    #[cfg(feature = "front-matter")]
+    #[test]
+    fn test_front_matter_metadata() {
+        let recipe = Recipe::try_from(
+            "---\nname: hummus\nservings: 2\ntags: [vegan]\n---\n>> servings: 4\n>> tags: easy\nBlend\n---\nstory",
+        )
+        .unwrap();
+        assert_eq!(recipe.name.as_deref(), Some("hummus"));
+        assert_eq!(recipe.metadata.servings(), Some(4));
+        assert_eq!(recipe.metadata.tags(), vec!["vegan", "easy"]);
+        assert_eq!(recipe.backstory.as_deref(), Some("story"));
+    }
+
+    #[cfg(feature = "front-matter")]
+    #[test]
+    fn test_invalid_front_matter() {
+        // read like before front matter was supported, the last fence is a backstory
+        let recipe = Recipe::try_from("---\nname: [hummus\n---\nBlend").unwrap();
+        assert_eq!(recipe.name, None);
+        assert_eq!(recipe.backstory.as_deref(), Some("Blend"));
+        let recipe = Recipe::try_from("---\nBlend {chickpeas}\n---\nA family recipe").unwrap();
+        assert_eq!(recipe.ingredients[0].name, "chickpeas");
+        assert_eq!(recipe.backstory.as_deref(), Some("A family recipe"));
+    }
+
+    #[test]
+    fn test_sections() {
+        let recipe = Recipe::try_from(
+            ">> name: cake\n== Sponge ==\nMix {flour}(200 gr) in a &{bowl}\n\n== Filling ==\nWhip {cream} for t{5 minutes}\n---\nstory",
+        )
+        .unwrap();
+        assert!(recipe.has_sections());
+        let names: Vec<_> = recipe
+            .sections
+            .iter()
+            .map(|section| section.name.as_deref())
+            .collect();
+        assert_eq!(names, vec![Some("Sponge"), Some("Filling")]);
+        assert_eq!(recipe.sections[0].ingredients[0].name, "flour");
+        assert_eq!(recipe.sections[0].materials[0].name, "bowl");
+        assert_eq!(recipe.sections[1].ingredients[0].name, "cream");
+        assert_eq!(recipe.sections[1].timers[0].duration, "5 minutes");
+        assert_eq!(recipe.ingredients.len(), 2);
+    }
+
+    #[test]
+    fn test_temperatures() {
+        let recipe =
+            Recipe::try_from("== Bread ==\nBake at °{220 C}, then at °{gas mark 4}").unwrap();
+        let temperatures: Vec<_> = recipe.temperatures.iter().map(|t| t.to_string()).collect();
+        assert_eq!(temperatures, vec!["220 °C", "gas mark 4"]);
+        assert_eq!(recipe.sections[0].temperatures.len(), 2);
+        let fahrenheit: Vec<_> = recipe
+            .temperatures
+            .iter()
+            .filter_map(|t| t.convert_to(TemperatureScale::Fahrenheit))
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(fahrenheit, vec!["428 °F", "350 °F"]);
+        assert_eq!(
+            recipe.temperatures[0].convert_to(TemperatureScale::GasMark),
+            Some(Temperature {
+                value: "7".to_string(),
+                scale: TemperatureScale::GasMark
+            })
+        );
+    }
+
+    #[test]
+    fn test_without_sections() {
+        let recipe = Recipe::try_from("Mix {flour}(200 gr)").unwrap();
+        assert!(!recipe.has_sections());
+        assert_eq!(recipe.sections.len(), 1);
+        assert_eq!(recipe.sections[0].ingredients, recipe.ingredients);
+    }
+
+    #[test]
+    fn test_alternative_amounts() {
+        let recipe = Recipe::try_from("Add {chickpeas}(1 can | 400 gr | 2 cups)").unwrap();
+        let chickpeas = &recipe.ingredients[0];
+        assert_eq!(chickpeas.amounts().count(), 3);
+
+        let mass = chickpeas.amount_in(Dimension::Mass).unwrap();
+        assert_eq!(mass.unit.as_deref(), Some("gr"));
+        assert_eq!(mass.convert_to("kg"), Some(0.4));
+
+        let volume = chickpeas.amount_in(Dimension::Volume).unwrap();
+        assert_eq!(volume.to_base(), Some((480.0, Dimension::Volume)));
+
+        let doubled = chickpeas.scale(2.0);
+        assert_eq!(doubled.quantity.as_deref(), Some("2"));
+        assert_eq!(doubled.alternatives[0].quantity.as_deref(), Some("800"));
+    }
 }
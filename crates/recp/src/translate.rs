@@ -0,0 +1,871 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use recipe_parser::{Token, escape, parse, parse_with_spans, unescape};
+
+use crate::metadata::normalize_key;
+
+/// Metadata whose values are not text to translate
+const UNTRANSLATABLE_KEYS: &[&str] = &[
+    "lang",
+    "tags",
+    "servings",
+    "yield",
+    "prep-time",
+    "cook-time",
+    "total-time",
+    "difficulty",
+    "author",
+    "source",
+    "url",
+    "image",
+    recipe_parser::VERSION_KEY,
+];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SegmentKind {
+    Metadata,
+    Section,
+    /// A paragraph of the instructions, its ingredients, timers and materials are placeholders
+    Step,
+    Ingredient,
+    Material,
+    Backstory,
+}
+
+impl SegmentKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SegmentKind::Metadata => "metadata",
+            SegmentKind::Section => "section",
+            SegmentKind::Step => "step",
+            SegmentKind::Ingredient => "ingredient",
+            SegmentKind::Material => "material",
+            SegmentKind::Backstory => "backstory",
+        }
+    }
+}
+
+/// A text to translate.
+///
+/// The id is made of the kind and a hash of the source text, like
+/// `ingredient:5f3a0c1e`, so it stays the same while the text doesn't change,
+/// wherever it moves in the recipe. A text repeated in the recipe gets the
+/// number of the occurrence, like `ingredient:5f3a0c1e:2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub id: String,
+    pub kind: SegmentKind,
+    /// Extra context for the translators, like the metadata key
+    pub comment: Option<String>,
+    pub source: String,
+    /// Empty while it's not translated
+    pub translation: String,
+}
+
+/// The translatable segments of a recipe, written as a gettext PO file.
+///
+/// The id of each segment is its `msgctxt`. In the steps, `{1}`, `{2}`... stand for
+/// the ingredients, timers and materials, and must be kept in the translation:
+///
+/// ```text
+/// msgctxt "step:9b1d0f3a"
+/// msgid "Blend {1} for {2}."
+/// msgstr "Licuar {1} durante {2}."
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    /// Language of the translations, written as `lang` in the translated recipe
+    pub lang: Option<String>,
+    pub segments: Vec<Segment>,
+}
+
+/// A translation that can't be applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationIssue {
+    pub id: String,
+    pub reason: String,
+}
+
+impl Display for TranslationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.id, self.reason)
+    }
+}
+
+/// Escape a string for a PO file
+fn po_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn po_unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// A PO string, split in lines after each line break
+fn po_string(keyword: &str, value: &str) -> String {
+    if !value.contains('\n') {
+        return format!("{keyword} \"{}\"\n", po_escape(value));
+    }
+    let mut out = format!("{keyword} \"\"\n");
+    for line in value.split_inclusive('\n') {
+        out.push_str(&format!("\"{}\"\n", po_escape(line)));
+    }
+    out
+}
+
+impl Catalog {
+    pub fn to_po(&self) -> String {
+        let mut out = String::from("msgid \"\"\nmsgstr \"\"\n");
+        out.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+        if let Some(lang) = &self.lang {
+            out.push_str(&format!("\"Language: {}\\n\"\n", po_escape(lang)));
+        }
+        for segment in &self.segments {
+            out.push('\n');
+            let comment = match &segment.comment {
+                Some(comment) => format!("{} {comment}", segment.kind.name()),
+                None => segment.kind.name().to_string(),
+            };
+            out.push_str(&format!("#. {comment}\n"));
+            out.push_str(&po_string("msgctxt", &segment.id));
+            out.push_str(&po_string("msgid", &segment.source));
+            out.push_str(&po_string("msgstr", &segment.translation));
+        }
+        out
+    }
+
+    /// Read a PO file, the kind of each segment comes from its id
+    pub fn parse_po(content: &str) -> Result<Catalog, String> {
+        let mut catalog = Catalog::default();
+        // the fields of the entry being read, and the one the next strings continue
+        let mut entry: HashMap<&str, String> = HashMap::new();
+        let mut current: Option<&str> = None;
+        let mut finish = |entry: &mut HashMap<&str, String>| -> Result<(), String> {
+            if entry.is_empty() {
+                return Ok(());
+            }
+            let id = entry.remove("msgctxt").unwrap_or_default();
+            let source = entry.remove("msgid").unwrap_or_default();
+            let translation = entry.remove("msgstr").unwrap_or_default();
+            entry.clear();
+            if id.is_empty() && source.is_empty() {
+                catalog.lang = translation
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Language:"))
+                    .map(|lang| lang.trim().to_string())
+                    .filter(|lang| !lang.is_empty());
+                return Ok(());
+            }
+            let kind = match id.split(':').next() {
+                Some("metadata") => SegmentKind::Metadata,
+                Some("section") => SegmentKind::Section,
+                Some("step") => SegmentKind::Step,
+                Some("ingredient") => SegmentKind::Ingredient,
+                Some("material") => SegmentKind::Material,
+                Some("backstory") => SegmentKind::Backstory,
+                _ => return Err(format!("unknown segment id {id:?}")),
+            };
+            catalog.segments.push(Segment {
+                id,
+                kind,
+                comment: None,
+                source,
+                translation,
+            });
+            Ok(())
+        };
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            let invalid = || format!("line {}: invalid PO entry", number + 1);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(string) = line.strip_prefix('"') {
+                let string = string.strip_suffix('"').ok_or_else(invalid)?;
+                let field = current.ok_or_else(invalid)?;
+                entry
+                    .entry(field)
+                    .or_default()
+                    .push_str(&po_unescape(string));
+                continue;
+            }
+            let (keyword, string) = line.split_once(' ').ok_or_else(invalid)?;
+            let keyword = match keyword {
+                "msgctxt" => "msgctxt",
+                "msgid" => "msgid",
+                "msgstr" => "msgstr",
+                _ => return Err(invalid()),
+            };
+            // a new entry starts with its context or its id
+            if keyword == "msgctxt" || (keyword == "msgid" && entry.contains_key("msgstr")) {
+                finish(&mut entry)?;
+            }
+            let string = string
+                .trim()
+                .strip_prefix('"')
+                .and_then(|string| string.strip_suffix('"'))
+                .ok_or_else(invalid)?;
+            entry.insert(keyword, po_unescape(string));
+            current = Some(keyword);
+        }
+        finish(&mut entry)?;
+        Ok(catalog)
+    }
+
+    /// The translated segments by id, leaving out the untranslated ones
+    fn translations(&self) -> HashMap<&str, &Segment> {
+        self.segments
+            .iter()
+            .filter(|segment| !segment.translation.is_empty())
+            .map(|segment| (segment.id.as_str(), segment))
+            .collect()
+    }
+}
+
+/// Byte offset of a slice of the source
+fn offset(source: &str, slice: &str) -> usize {
+    slice.as_ptr() as usize - source.as_ptr() as usize
+}
+
+/// FNV-1a hash of a text, which unlike the std hasher doesn't change between Rust versions
+fn text_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Gives the ids of the segments, in the order of the recipe
+#[derive(Default)]
+struct SegmentIds(HashMap<(SegmentKind, String), usize>);
+
+impl SegmentIds {
+    fn next(&mut self, kind: SegmentKind, text: &str) -> String {
+        let count = self.0.entry((kind, text.to_string())).or_default();
+        *count += 1;
+        let id = format!("{}:{:08x}", kind.name(), text_hash(text));
+        match count {
+            1 => id,
+            count => format!("{id}:{count}"),
+        }
+    }
+}
+
+/// The recipe split in the parts that are translated separately
+enum Part<'a> {
+    /// Written as it is: front matter, blank lines, metadata not translated
+    Raw(Range<usize>),
+    Metadata {
+        key: &'a str,
+        value: &'a str,
+        span: Range<usize>,
+    },
+    Section {
+        name: &'a str,
+        span: Range<usize>,
+    },
+    Backstory {
+        text: &'a str,
+        span: Range<usize>,
+    },
+    Step(Vec<(Token<'a>, Range<usize>)>),
+}
+
+fn flush_step<'a>(parts: &mut Vec<Part<'a>>, step: &mut Vec<(Token<'a>, Range<usize>)>) {
+    let mut trailing = Vec::new();
+    while let Some((Token::Space(_), span)) = step.last() {
+        trailing.push(span.clone());
+        step.pop();
+    }
+    if !step.is_empty() {
+        parts.push(Part::Step(std::mem::take(step)));
+    }
+    parts.extend(trailing.into_iter().rev().map(Part::Raw));
+}
+
+fn parts(source: &str) -> Result<Vec<Part<'_>>, String> {
+    let tokens = parse_with_spans(source).map_err(|error| error.to_string())?;
+    let mut parts = Vec::new();
+    let mut step = Vec::new();
+    for (token, span) in tokens {
+        let part = match token {
+            Token::Space(space) if step.is_empty() || space.matches('\n').count() > 1 => {
+                Part::Raw(span)
+            }
+            Token::FrontMatter { .. } => Part::Raw(span),
+            Token::Metadata { key, value }
+                if !UNTRANSLATABLE_KEYS.contains(&normalize_key(key).as_str())
+                    && !value.trim().is_empty() =>
+            {
+                Part::Metadata { key, value, span }
+            }
+            Token::Metadata { .. } => Part::Raw(span),
+            Token::Section(name) => Part::Section { name, span },
+            Token::Backstory(text) => Part::Backstory { text, span },
+            token => {
+                step.push((token, span));
+                continue;
+            }
+        };
+        flush_step(&mut parts, &mut step);
+        parts.push(part);
+    }
+    flush_step(&mut parts, &mut step);
+    Ok(parts)
+}
+
+/// The translatable name of a token of a step: ingredients and materials
+fn token_name<'a>(token: &Token<'a>) -> Option<(SegmentKind, &'a str)> {
+    match token {
+        Token::Ingredient { name, .. } => Some((SegmentKind::Ingredient, name)),
+        Token::Material(name) => Some((SegmentKind::Material, name)),
+        _ => None,
+    }
+}
+
+/// The text of a step, with `{1}`, `{2}`... in place of its tokens
+fn step_template(source: &str, step: &[(Token, Range<usize>)]) -> (String, usize) {
+    let mut template = String::new();
+    let mut placeholders = 0;
+    for (token, span) in step {
+        match token {
+            Token::Word(_) | Token::Space(_) => template.push_str(&source[span.clone()]),
+            _ => {
+                placeholders += 1;
+                template.push_str(&format!("{{{placeholders}}}"));
+            }
+        }
+    }
+    (template, placeholders)
+}
+
+/// Whether a step has text to translate, a step of only ingredients or timers doesn't
+fn has_text(template: &str, placeholders: usize) -> bool {
+    template.chars().any(char::is_alphabetic) || placeholders == 0
+}
+
+/// Extract the texts to translate of a recipe
+pub fn extract(source: &str) -> Result<Catalog, String> {
+    let mut segments = Vec::new();
+    let mut ids = SegmentIds::default();
+    let mut push = |kind: SegmentKind, comment: Option<String>, text: String| {
+        segments.push(Segment {
+            id: ids.next(kind, &text),
+            kind,
+            comment,
+            source: text,
+            translation: String::new(),
+        })
+    };
+    for part in parts(source)? {
+        match part {
+            Part::Raw(_) => {}
+            Part::Metadata { key, value, .. } => push(
+                SegmentKind::Metadata,
+                Some(key.trim().to_string()),
+                value.to_string(),
+            ),
+            Part::Section { name, .. } => {
+                push(SegmentKind::Section, None, unescape(name).into_owned())
+            }
+            Part::Backstory { text, .. } => push(SegmentKind::Backstory, None, text.to_string()),
+            Part::Step(step) => {
+                let (template, placeholders) = step_template(source, &step);
+                if has_text(&template, placeholders) {
+                    push(SegmentKind::Step, None, template);
+                }
+                for (token, _) in &step {
+                    if let Some((kind, name)) = token_name(token) {
+                        push(kind, None, unescape(name).into_owned());
+                    }
+                }
+            }
+        }
+    }
+    Ok(Catalog {
+        lang: None,
+        segments,
+    })
+}
+
+/// The placeholders of a translated step, checked against the number of tokens
+fn check_placeholders(translation: &str, placeholders: usize) -> Result<(), String> {
+    let mut seen = vec![0; placeholders];
+    let mut rest = translation;
+    while let Some(start) = rest.find('{') {
+        let escaped = start > 0 && rest[..start].ends_with('\\');
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else { break };
+        let Ok(number) = rest[..end].parse::<usize>() else {
+            continue;
+        };
+        if escaped {
+            continue;
+        }
+        match seen.get_mut(number.wrapping_sub(1)) {
+            Some(count) => *count += 1,
+            None => return Err(format!("{{{number}}} is not a token of the step")),
+        }
+    }
+    for (index, count) in seen.into_iter().enumerate() {
+        match count {
+            0 => return Err(format!("{{{}}} is missing", index + 1)),
+            1 => {}
+            _ => return Err(format!("{{{}}} is repeated", index + 1)),
+        }
+    }
+    Ok(())
+}
+
+/// Replace the placeholders of a translated step by the tokens, in a single pass,
+/// so a token that looks like a placeholder, like the ingredient `{2}`, stays as it is
+fn fill_placeholders(translation: &str, tokens: &[&str]) -> String {
+    let mut text = String::with_capacity(translation.len());
+    let mut rest = translation;
+    while let Some(start) = rest.find('{') {
+        let escaped = rest[..start].ends_with('\\');
+        let after = &rest[start + 1..];
+        let token = after.find('}').filter(|_| !escaped).and_then(|end| {
+            let number = after[..end].parse::<usize>().ok()?;
+            Some((tokens.get(number.checked_sub(1)?)?, end))
+        });
+        match token {
+            Some((token, end)) => {
+                text.push_str(&rest[..start]);
+                text.push_str(token);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// What a token means for the recipe, leaving out the names that can be translated
+fn structure(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Ingredient {
+                quantity,
+                unit,
+                alternatives,
+                optional,
+                ..
+            } => Some(format!(
+                "ingredient {quantity:?} {unit:?} {alternatives:?} {optional}"
+            )),
+            Token::RecipeRef { .. }
+            | Token::Timer(_)
+            | Token::Tag { .. }
+            | Token::Temperature { .. }
+            | Token::Comment(_) => Some(format!("{token:?}")),
+            Token::Material(_) => Some("material".to_string()),
+            Token::Metadata { .. } | Token::Section(_) | Token::Backstory(_) => {
+                Some(format!("{token:?}"))
+            }
+            Token::FrontMatter { .. } => Some("front matter".to_string()),
+            Token::Word(_) | Token::Space(_) => None,
+        })
+        .collect()
+}
+
+/// Build the translated recipe, or report the translations that would break it
+fn build(source: &str, catalog: &Catalog) -> Result<String, Vec<TranslationIssue>> {
+    let translations = catalog.translations();
+    let mut ids = SegmentIds::default();
+    // the id and the translation of a segment, if its entry was made for the same text
+    let mut translate = |kind: SegmentKind, text: &str| {
+        let id = ids.next(kind, text);
+        let translation = translations
+            .get(id.as_str())
+            .filter(|segment| segment.source == text)
+            .map(|segment| segment.translation.as_str());
+        (id, translation)
+    };
+    let mut issues = Vec::new();
+    let mut out = String::new();
+    let parts = parts(source).map_err(|reason| {
+        vec![TranslationIssue {
+            id: "recipe".to_string(),
+            reason,
+        }]
+    })?;
+    let mut has_lang = false;
+    for part in parts {
+        match part {
+            Part::Raw(span) => {
+                let raw = &source[span.clone()];
+                match parse(raw).ok().as_deref() {
+                    Some([Token::Metadata { key, value }]) if normalize_key(key) == "lang" => {
+                        has_lang = true;
+                        let lang = catalog.lang.as_deref().unwrap_or(value);
+                        out.push_str(&raw[..offset(raw, value)]);
+                        out.push_str(lang);
+                    }
+                    _ => out.push_str(raw),
+                }
+            }
+            Part::Metadata { value, span, .. } => {
+                let (id, translation) = translate(SegmentKind::Metadata, value);
+                let prefix = &source[span.start..offset(source, value)];
+                match translation {
+                    Some(translation) if translation.contains('\n') => {
+                        issues.push(TranslationIssue {
+                            id,
+                            reason: "must be a single line".to_string(),
+                        });
+                    }
+                    Some(translation) => {
+                        out.push_str(prefix);
+                        out.push_str(translation);
+                    }
+                    None => out.push_str(&source[span]),
+                }
+            }
+            Part::Section { name, span } => {
+                let (id, translation) = translate(SegmentKind::Section, &unescape(name));
+                match translation {
+                    Some(translation) if translation.contains('\n') => {
+                        issues.push(TranslationIssue {
+                            id,
+                            reason: "must be a single line".to_string(),
+                        });
+                    }
+                    Some(translation) => out.push_str(&format!("== {} ==", escape(translation))),
+                    None => out.push_str(&source[span]),
+                }
+            }
+            Part::Backstory { text, span } => {
+                let (_, translation) = translate(SegmentKind::Backstory, text);
+                out.push_str(&source[span.start..offset(source, text)]);
+                out.push_str(translation.unwrap_or(text));
+            }
+            Part::Step(step) => {
+                // the tokens with their translated names
+                let mut rendered = Vec::new();
+                for (token, span) in &step {
+                    let Some((kind, name)) = token_name(token) else {
+                        rendered.push(source[span.clone()].to_string());
+                        continue;
+                    };
+                    let (id, translation) = translate(kind, &unescape(name));
+                    let name_start = offset(source, name);
+                    match translation {
+                        Some(translation) if translation.contains('\n') => {
+                            issues.push(TranslationIssue {
+                                id,
+                                reason: "must be a single line".to_string(),
+                            });
+                            rendered.push(source[span.clone()].to_string());
+                        }
+                        Some(translation) => rendered.push(format!(
+                            "{}{}{}",
+                            &source[span.start..name_start],
+                            escape(translation),
+                            &source[name_start + name.len()..span.end]
+                        )),
+                        None => rendered.push(source[span.clone()].to_string()),
+                    }
+                }
+                let (template, placeholders) = step_template(source, &step);
+                let (id, translation) = match has_text(&template, placeholders) {
+                    true => translate(SegmentKind::Step, &template),
+                    false => ("step".to_string(), None),
+                };
+                let text = match translation {
+                    Some(translation) => {
+                        if let Err(reason) = check_placeholders(translation, placeholders) {
+                            issues.push(TranslationIssue { id, reason });
+                            continue;
+                        }
+                        let tokens: Vec<&str> = step
+                            .iter()
+                            .zip(&rendered)
+                            .filter(|((token, _), _)| {
+                                !matches!(token, Token::Word(_) | Token::Space(_))
+                            })
+                            .map(|(_, rendered)| rendered.as_str())
+                            .collect();
+                        fill_placeholders(translation, &tokens)
+                    }
+                    None => rendered.concat(),
+                };
+                let original: Vec<Token> = step.iter().map(|(token, _)| token.clone()).collect();
+                match parse(&text) {
+                    Ok(tokens) if structure(&tokens) == structure(&original) => {
+                        out.push_str(&text);
+                    }
+                    _ => issues.push(TranslationIssue {
+                        id,
+                        reason: "the translation changes the ingredients, timers or materials of the step"
+                            .to_string(),
+                    }),
+                }
+            }
+        }
+    }
+    if !issues.is_empty() {
+        return Err(issues);
+    }
+    if let (Some(lang), false) = (&catalog.lang, has_lang) {
+        let start = match parse_with_spans(source).ok().as_deref() {
+            Some([(Token::FrontMatter { .. }, span), ..]) => span.end,
+            _ => 0,
+        };
+        out.insert_str(start, &format!(">> lang: {lang}\n"));
+    }
+    Ok(out)
+}
+
+/// Report the entries of the catalog made for texts that are not in the recipe anymore,
+/// they are left out when the translations are applied
+pub fn stale(source: &str, catalog: &Catalog) -> Vec<TranslationIssue> {
+    let Ok(current) = extract(source) else {
+        return Vec::new();
+    };
+    let sources: HashMap<&str, &str> = current
+        .segments
+        .iter()
+        .map(|segment| (segment.id.as_str(), segment.source.as_str()))
+        .collect();
+    catalog
+        .segments
+        .iter()
+        .filter_map(|segment| {
+            let reason = match sources.get(segment.id.as_str()) {
+                None => "matches no text of the recipe",
+                Some(source) if *source != segment.source => "the text of the recipe changed",
+                Some(_) => return None,
+            };
+            Some(TranslationIssue {
+                id: segment.id.clone(),
+                reason: format!("stale, {reason}"),
+            })
+        })
+        .collect()
+}
+
+/// Report the translations that would break the token structure of the recipe,
+/// and the stale entries of the catalog
+pub fn check(source: &str, catalog: &Catalog) -> Vec<TranslationIssue> {
+    let mut issues = build(source, catalog).err().unwrap_or_default();
+    issues.extend(stale(source, catalog));
+    issues
+}
+
+/// Rebuild the recipe with the translations of the catalog.
+///
+/// The amounts, timers and the rest of the tokens are kept, and untranslated
+/// segments are left in the source language, like the [stale] ones. The `lang` metadata is set to the
+/// language of the catalog.
+pub fn apply(source: &str, catalog: &Catalog) -> Result<String, Vec<TranslationIssue>> {
+    build(source, catalog)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RECIPE: &str = ">> name: hummus
+>> servings: 2
+
+Add {boiled chickpeas}(400 gr) to the &{blender}.
+
+Blend for t{3 minutes}.
+---
+From my grandmother.";
+
+    fn translate(catalog: &mut Catalog, translations: &[(&str, &str)]) {
+        for (source, translation) in translations {
+            let segment = catalog
+                .segments
+                .iter_mut()
+                .find(|segment| segment.source == *source)
+                .unwrap();
+            segment.translation = translation.to_string();
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let catalog = extract(RECIPE).unwrap();
+        let segments: Vec<_> = catalog
+            .segments
+            .iter()
+            .map(|segment| (segment.id.as_str(), segment.source.as_str()))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                ("metadata:7160acc0", "hummus"),
+                ("step:4f2fe547", "Add {1} to the {2}."),
+                ("ingredient:6b64da91", "boiled chickpeas"),
+                ("material:848b4caf", "blender"),
+                ("step:99b2bb6e", "Blend for {1}."),
+                ("backstory:22b53e0e", "From my grandmother."),
+            ]
+        );
+        let ids: Vec<_> = extract("Mix {salt}.\n\nServe with {salt}.")
+            .unwrap()
+            .segments
+            .into_iter()
+            .filter(|segment| segment.kind == SegmentKind::Ingredient)
+            .map(|segment| segment.id)
+            .collect();
+        assert_eq!(ids, vec!["ingredient:c20e7781", "ingredient:c20e7781:2"]);
+    }
+
+    #[test]
+    fn test_po_roundtrip() {
+        let mut catalog = extract(RECIPE).unwrap();
+        catalog.lang = Some("es".to_string());
+        translate(
+            &mut catalog,
+            &[("Add {1} to the {2}.", "Poner {1} en la\n{2}.")],
+        );
+        let po = catalog.to_po();
+        assert!(po.contains("msgctxt \"step:4f2fe547\"\nmsgid \"Add {1} to the {2}.\"\n"));
+        let mut parsed = Catalog::parse_po(&po).unwrap();
+        for (segment, original) in parsed.segments.iter_mut().zip(&catalog.segments) {
+            segment.comment = original.comment.clone();
+        }
+        assert_eq!(parsed, catalog);
+        assert!(Catalog::parse_po("msgid \"unclosed").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut catalog = extract(RECIPE).unwrap();
+        catalog.lang = Some("es".to_string());
+        translate(
+            &mut catalog,
+            &[
+                ("hummus", "hummus clásico"),
+                ("Add {1} to the {2}.", "Poner {1} en la {2}."),
+                ("boiled chickpeas", "garbanzos cocidos"),
+                ("blender", "licuadora"),
+                ("From my grandmother.", "De mi abuela."),
+            ],
+        );
+        assert_eq!(
+            apply(RECIPE, &catalog).unwrap(),
+            ">> lang: es
+>> name: hummus clásico
+>> servings: 2
+
+Poner {garbanzos cocidos}(400 gr) en la &{licuadora}.
+
+Blend for t{3 minutes}.
+---
+De mi abuela."
+        );
+    }
+
+    #[test]
+    fn test_apply_tokens_like_placeholders() {
+        let recipe = "Mix {2} with {milk}.";
+        let mut catalog = extract(recipe).unwrap();
+        translate(
+            &mut catalog,
+            &[("Mix {1} with {2}.", "Mezclar {1} con {2}.")],
+        );
+        assert_eq!(apply(recipe, &catalog).unwrap(), "Mezclar {2} con {milk}.");
+        assert_eq!(
+            fill_placeholders(r"{2} \{1} {x} {1}", &["a", "b"]),
+            r"b \{1} {x} a"
+        );
+    }
+
+    #[test]
+    fn test_check_token_structure() {
+        let mut catalog = extract(RECIPE).unwrap();
+        translate(
+            &mut catalog,
+            &[
+                ("Add {1} to the {2}.", "Poner {1} en la licuadora."),
+                ("Blend for {1}.", "Licuar por {1} {y más}."),
+            ],
+        );
+        let issues: Vec<String> = check(RECIPE, &catalog)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "step:4f2fe547: {2} is missing",
+                "step:99b2bb6e: the translation changes the ingredients, timers or materials of the step"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stale_translations() {
+        let mut catalog = extract(RECIPE).unwrap();
+        translate(
+            &mut catalog,
+            &[
+                ("Add {1} to the {2}.", "Poner {1} en la {2}."),
+                ("Blend for {1}.", "Licuar por {1}."),
+            ],
+        );
+
+        // the ids don't depend on the lines
+        let moved = format!(">> author: me\n{RECIPE}");
+        assert_eq!(check(&moved, &catalog), vec![]);
+        assert!(
+            apply(&moved, &catalog)
+                .unwrap()
+                .contains("Licuar por t{3 minutes}.")
+        );
+
+        let changed = RECIPE.replace("Add {", "Never add {");
+        let issues: Vec<String> = check(&changed, &catalog)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec!["step:4f2fe547: stale, matches no text of the recipe"]
+        );
+        let translated = apply(&changed, &catalog).unwrap();
+        assert!(translated.contains("Never add {boiled chickpeas}(400 gr) to the &{blender}."));
+        assert!(translated.contains("Licuar por t{3 minutes}."));
+
+        // an entry whose text was edited is not applied
+        catalog.segments[4].source = "Blend well for {1}.".to_string();
+        assert!(
+            apply(RECIPE, &catalog)
+                .unwrap()
+                .contains("Blend for t{3 minutes}.")
+        );
+        assert_eq!(
+            check(RECIPE, &catalog)[0].to_string(),
+            "step:99b2bb6e: stale, the text of the recipe changed"
+        );
+    }
+}
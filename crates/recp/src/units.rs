@@ -0,0 +1,382 @@
+use std::time::Duration;
+
+use recipe_parser::TemperatureScale;
+
+use crate::locale::LOCALES;
+
+/// What a unit measures
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Dimension {
+    Mass,
+    Volume,
+    /// Amounts without a unit, like `{eggs}(2)`
+    Count,
+}
+
+/// A known unit and its factor to the base unit of its dimension.
+///
+/// The base units are grams for mass, milliliters for volume and pieces for count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub symbol: &'static str,
+    pub dimension: Dimension,
+    pub factor: f64,
+    aliases: &'static [&'static str],
+}
+
+const fn unit(
+    symbol: &'static str,
+    dimension: Dimension,
+    factor: f64,
+    aliases: &'static [&'static str],
+) -> Unit {
+    Unit {
+        symbol,
+        dimension,
+        factor,
+        aliases,
+    }
+}
+
+pub const UNITS: &[Unit] = &[
+    unit("mg", Dimension::Mass, 0.001, &["milligram", "milligrams"]),
+    unit(
+        "g",
+        Dimension::Mass,
+        1.0,
+        &["gr", "grs", "gram", "grams", "gramme", "grammes"],
+    ),
+    unit(
+        "kg",
+        Dimension::Mass,
+        1000.0,
+        &["kgs", "kilo", "kilos", "kilogram", "kilograms"],
+    ),
+    unit("oz", Dimension::Mass, 28.3495, &["ounce", "ounces"]),
+    unit("lb", Dimension::Mass, 453.592, &["lbs", "pound", "pounds"]),
+    unit(
+        "ml",
+        Dimension::Volume,
+        1.0,
+        &["milliliter", "milliliters", "millilitre", "millilitres"],
+    ),
+    unit(
+        "cl",
+        Dimension::Volume,
+        10.0,
+        &["centiliter", "centiliters", "centilitre", "centilitres"],
+    ),
+    unit(
+        "dl",
+        Dimension::Volume,
+        100.0,
+        &["deciliter", "deciliters", "decilitre", "decilitres"],
+    ),
+    unit(
+        "l",
+        Dimension::Volume,
+        1000.0,
+        &["liter", "liters", "litre", "litres"],
+    ),
+    unit(
+        "tsp",
+        Dimension::Volume,
+        4.92892,
+        &["teaspoon", "teaspoons"],
+    ),
+    unit(
+        "tbsp",
+        Dimension::Volume,
+        14.7868,
+        &["tablespoon", "tablespoons"],
+    ),
+    unit("cup", Dimension::Volume, 240.0, &["cups"]),
+    unit(
+        "fl oz",
+        Dimension::Volume,
+        29.5735,
+        &["fluid ounce", "fluid ounces"],
+    ),
+];
+
+/// Find a known unit by its symbol or name, e.g: `gr`, `Kilograms`, `tbsp`.
+///
+/// The names of the bundled locales are known too, like `cucharada` or `グラム`.
+pub fn lookup_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim().to_lowercase();
+    let symbol = LOCALES
+        .iter()
+        .flat_map(|locale| locale.units)
+        .find(|(_, names)| names.contains(&name.as_str()))
+        .map_or(name.as_str(), |(symbol, _)| symbol);
+    UNITS
+        .iter()
+        .find(|unit| unit.symbol == symbol || unit.aliases.contains(&symbol))
+}
+
+/// Parse the quantity of an amount into a number.
+///
+/// Supports the quantities allowed by recipe-lang: `3`, `1.2`, `1,2`, `1_000`, `1/2` and `.5`.
+pub fn parse_quantity(quantity: &str) -> Option<f64> {
+    let quantity = quantity.trim().replace('_', "");
+    if let Some((numerator, denominator)) = quantity.split_once('/') {
+        let numerator = parse_quantity(numerator)?;
+        let denominator = parse_quantity(denominator)?;
+        if denominator == 0.0 {
+            return None;
+        }
+        return Some(numerator / denominator);
+    }
+    quantity.replace(',', ".").parse::<f64>().ok()
+}
+
+/// Format a number as a quantity, without trailing zeros
+pub fn format_quantity(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    let formatted = format!("{rounded:.2}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Fahrenheit of the gas marks below 1, the marks above go up 25 °F each
+const LOW_GAS_MARKS: &[(f64, f64)] = &[(0.25, 225.0), (0.5, 250.0), (1.0, 275.0)];
+
+fn gas_mark_to_fahrenheit(mark: f64) -> Option<f64> {
+    if mark >= 1.0 {
+        return Some(250.0 + 25.0 * mark);
+    }
+    LOW_GAS_MARKS
+        .windows(2)
+        .find(|pair| mark >= pair[0].0 && mark <= pair[1].0)
+        .map(|pair| {
+            let ((low_mark, low), (high_mark, high)) = (pair[0], pair[1]);
+            low + (mark - low_mark) / (high_mark - low_mark) * (high - low)
+        })
+}
+
+fn fahrenheit_to_gas_mark(fahrenheit: f64) -> Option<f64> {
+    if fahrenheit >= 275.0 {
+        return Some((fahrenheit - 250.0) / 25.0);
+    }
+    LOW_GAS_MARKS
+        .windows(2)
+        .find(|pair| fahrenheit >= pair[0].1 && fahrenheit <= pair[1].1)
+        .map(|pair| {
+            let ((low_mark, low), (high_mark, high)) = (pair[0], pair[1]);
+            low_mark + (fahrenheit - low) / (high - low) * (high_mark - low_mark)
+        })
+}
+
+/// Convert a temperature between scales, going through celsius.
+///
+/// Ovens don't have gas marks below 1/4 (225 °F), so there's no conversion for them.
+pub fn convert_temperature(
+    value: f64,
+    from: TemperatureScale,
+    to: TemperatureScale,
+) -> Option<f64> {
+    let celsius = match from {
+        TemperatureScale::Celsius => value,
+        TemperatureScale::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        TemperatureScale::Kelvin => value - 273.15,
+        TemperatureScale::GasMark => (gas_mark_to_fahrenheit(value)? - 32.0) * 5.0 / 9.0,
+    };
+    match to {
+        TemperatureScale::Celsius => Some(celsius),
+        TemperatureScale::Fahrenheit => Some(celsius * 9.0 / 5.0 + 32.0),
+        TemperatureScale::Kelvin => Some(celsius + 273.15),
+        TemperatureScale::GasMark => fahrenheit_to_gas_mark(celsius * 9.0 / 5.0 + 32.0),
+    }
+}
+
+/// Format a temperature in whole degrees, and gas marks as `1/4`, `1/2` or a whole mark
+pub fn format_temperature(value: f64, scale: TemperatureScale) -> String {
+    match scale {
+        TemperatureScale::GasMark if value < 0.375 => "1/4".to_string(),
+        TemperatureScale::GasMark if value < 0.75 => "1/2".to_string(),
+        // `+ 0.0` turns `-0` into `0`
+        _ => format!("{}", value.round() + 0.0),
+    }
+}
+
+/// Seconds of a time unit, in any of the bundled locales
+fn time_unit_seconds(name: &str) -> Option<f64> {
+    let name = name.to_lowercase();
+    LOCALES
+        .iter()
+        .flat_map(|locale| locale.time_units)
+        .find(|(_, names)| names.contains(&name.as_str()))
+        .map(|(seconds, _)| *seconds)
+}
+
+/// Parse an ISO 8601 duration like `PT1H30M`
+fn parse_iso_duration(value: &str) -> Option<Duration> {
+    let rest = value.strip_prefix('P')?;
+    let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+    let mut seconds = 0.0;
+    for (part, units) in [
+        (date, [('D', 86400.0)].as_slice()),
+        (time, [('H', 3600.0), ('M', 60.0), ('S', 1.0)].as_slice()),
+    ] {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+            } else {
+                let (_, factor) = units.iter().find(|(unit, _)| *unit == c)?;
+                seconds += number.parse::<f64>().ok()? * factor;
+                number.clear();
+            }
+        }
+        if !number.is_empty() {
+            return None;
+        }
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Parse a duration, like the ones used in timers and metadata.
+///
+/// Supports `25 minutes`, `1 hour 30 min`, `1h30m`, `1.5 hours` and ISO 8601 `PT1H30M`,
+/// and the time units of the bundled locales, like `1 hora y 30 minutos` or `5分`.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.starts_with('P') {
+        return parse_iso_duration(value);
+    }
+    let mut seconds = 0.0;
+    let mut pending: Option<f64> = None;
+    let mut found = false;
+    // split numbers from words: "1h30m" -> ["1", "h", "30", "m"]
+    let is_number = |c: char| c.is_ascii_digit() || matches!(c, '.' | ',' | '/');
+    let mut chunks: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    for c in value.chars() {
+        if !c.is_whitespace() {
+            match chunks.last_mut() {
+                Some(last)
+                    if previous
+                        .is_some_and(|p| !p.is_whitespace() && is_number(p) == is_number(c)) =>
+                {
+                    last.push(c)
+                }
+                _ => chunks.push(c.to_string()),
+            }
+        }
+        previous = Some(c);
+    }
+    let is_and = |chunk: &str| {
+        LOCALES
+            .iter()
+            .any(|locale| !locale.and.is_empty() && locale.and == chunk)
+    };
+    for chunk in chunks.iter().filter(|chunk| !is_and(chunk)) {
+        if let Some(number) = parse_quantity(chunk) {
+            if pending.is_some() {
+                return None;
+            }
+            pending = Some(number);
+        } else {
+            let unit = time_unit_seconds(chunk)?;
+            seconds += pending.take()? * unit;
+            found = true;
+        }
+    }
+    if pending.is_some() || !found {
+        return None;
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Format a duration like `1 h 30 min`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60);
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes} min"),
+        (hours, 0) => format!("{hours} h"),
+        (hours, minutes) => format!("{hours} h {minutes} min"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity("3"), Some(3.0));
+        assert_eq!(parse_quantity("1.5"), Some(1.5));
+        assert_eq!(parse_quantity("1,5"), Some(1.5));
+        assert_eq!(parse_quantity("1_000"), Some(1000.0));
+        assert_eq!(parse_quantity("1/2"), Some(0.5));
+        assert_eq!(parse_quantity(".5"), Some(0.5));
+        assert_eq!(parse_quantity("1/0"), None);
+        assert_eq!(parse_quantity("a few"), None);
+    }
+
+    #[test]
+    fn test_lookup_unit() {
+        assert_eq!(lookup_unit("gr").map(|u| u.symbol), Some("g"));
+        assert_eq!(lookup_unit("Kilograms").map(|u| u.symbol), Some("kg"));
+        assert_eq!(
+            lookup_unit("tbsp").map(|u| u.dimension),
+            Some(Dimension::Volume)
+        );
+        assert_eq!(lookup_unit("can"), None);
+        assert_eq!(lookup_unit("Cucharadas").map(|u| u.symbol), Some("tbsp"));
+        assert_eq!(lookup_unit("グラム").map(|u| u.symbol), Some("g"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+        assert_eq!(parse_duration("25 minutes"), minutes(25));
+        assert_eq!(parse_duration("1 hour"), minutes(60));
+        assert_eq!(parse_duration("1 hour and 30 min"), minutes(90));
+        assert_eq!(parse_duration("1h30m"), minutes(90));
+        assert_eq!(parse_duration("1.5 hours"), minutes(90));
+        assert_eq!(parse_duration("PT1H30M"), minutes(90));
+        assert_eq!(parse_duration("10 sec"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("a while"), None);
+        assert_eq!(parse_duration("PT1X"), None);
+        assert_eq!(parse_duration("1 hora y 30 minutos"), minutes(90));
+        assert_eq!(parse_duration("5分"), minutes(5));
+        assert_eq!(parse_duration("1時間30分"), minutes(90));
+        assert_eq!(parse_duration("2 Stunden"), minutes(120));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(90 * 60)), "1 h 30 min");
+        assert_eq!(format_duration(Duration::from_secs(20 * 60)), "20 min");
+        assert_eq!(format_duration(Duration::from_secs(120 * 60)), "2 h");
+    }
+
+    #[test]
+    fn test_convert_temperature() {
+        use TemperatureScale::*;
+        let convert = |value, from, to| {
+            convert_temperature(value, from, to).map(|value| format_temperature(value, to))
+        };
+        assert_eq!(convert(180.0, Celsius, Fahrenheit).as_deref(), Some("356"));
+        assert_eq!(convert(350.0, Fahrenheit, Celsius).as_deref(), Some("177"));
+        assert_eq!(convert(-18.0, Celsius, Kelvin).as_deref(), Some("255"));
+        assert_eq!(convert(180.0, Celsius, GasMark).as_deref(), Some("4"));
+        assert_eq!(convert(4.0, GasMark, Fahrenheit).as_deref(), Some("350"));
+        assert_eq!(convert(0.5, GasMark, Fahrenheit).as_deref(), Some("250"));
+        assert_eq!(convert(230.0, Fahrenheit, GasMark).as_deref(), Some("1/4"));
+        assert_eq!(convert(120.0, Celsius, GasMark).as_deref(), Some("1/2"));
+        assert_eq!(convert(100.0, Celsius, GasMark), None);
+        assert_eq!(convert(0.1, GasMark, Celsius), None);
+    }
+
+    #[test]
+    fn test_format_quantity() {
+        assert_eq!(format_quantity(2.0), "2");
+        assert_eq!(format_quantity(0.5), "0.5");
+        assert_eq!(format_quantity(1.0 / 3.0), "0.33");
+    }
+}